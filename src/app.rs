@@ -1,38 +1,124 @@
 use std::error;
 
 use crate::{
+    backend::DisplayBackend,
+    confirmation::Confirmation,
     help::Help,
-    notification::Notification,
-    xrandr::{Screen, Xrandr},
+    mode_picker::ModePicker,
+    notification::{Notification, NotificationLevel},
+    profile::{self, Profile, Profiles},
+    screen::{self, place_relative, Location, Screen, SNAP_DISTANCE},
+    ui::{self, Viewport},
 };
 
 pub type AppResult<T> = std::result::Result<T, Box<dyn error::Error>>;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone)]
+pub struct Drag {
+    pub index: usize,
+    /// The pending positions of the screens when the drag started: the layout
+    /// is shifted when a screen goes past the origin, so every move starts over
+    /// from them.
+    pub positions: Vec<Option<(u16, u16)>>,
+    /// The viewport when the drag started, kept so the layout does not move
+    /// under the cursor while the positions change.
+    pub viewport: Viewport,
+    /// Where the screen was grabbed, relative to its top left corner, in pixels.
+    pub grab: (f64, f64),
+    pub moved: bool,
+}
+
+#[derive(Debug)]
 pub struct App {
     pub running: bool,
+    pub backend: Box<dyn DisplayBackend>,
     pub notifications: Vec<Notification>,
     pub screens: Vec<Screen>,
     pub help: Help,
+    pub mode_picker: ModePicker,
+    pub profiles: Profiles,
+    /// Set while the last applied configuration waits for confirmation.
+    pub confirmation: Option<Confirmation>,
+    /// Only preview the changes, never apply them.
+    pub dry_run: bool,
+    /// Where the layout was last drawn.
+    pub viewport: Option<Viewport>,
+    /// The screen being dragged with the mouse.
+    pub drag: Option<Drag>,
+    /// Index of the screen that moves apply to.
+    pub selected: usize,
+    /// Index of the screen the selected one is placed relative to.
+    pub anchor: usize,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(backend: Box<dyn DisplayBackend>) -> Self {
         Self {
             running: true,
-            ..Default::default()
+            backend,
+            notifications: Vec::new(),
+            screens: Vec::new(),
+            help: Help::default(),
+            mode_picker: ModePicker::default(),
+            profiles: Profiles::new(profile::profiles_dir()),
+            confirmation: None,
+            dry_run: false,
+            viewport: None,
+            drag: None,
+            selected: 0,
+            anchor: 0,
         }
     }
 
-    pub fn get_all_screens(&self) -> Vec<Screen> {
-        Xrandr::get_screens()
+    pub fn get_all_screens(&self) -> AppResult<Vec<Screen>> {
+        self.backend.get_screens()
     }
 
     pub fn tick(&mut self) {
         self.notifications.retain(|n| n.ttl > 0);
         self.notifications.iter_mut().for_each(|n| n.ttl -= 1);
 
-        let screens = self.get_all_screens();
+        if self.confirmation.as_ref().is_some_and(|c| c.is_expired()) {
+            let (message, level) = match self.revert_configuration() {
+                Ok(()) => (
+                    "Configuration reverted".to_string(),
+                    NotificationLevel::Info,
+                ),
+                Err(e) => (e.to_string(), NotificationLevel::Error),
+            };
+            // Avoid stacking the same error while retrying on every tick
+            if !self.notifications.iter().any(|n| n.message == message) {
+                self.notifications.push(Notification {
+                    message,
+                    level,
+                    ttl: 8,
+                });
+            }
+        }
+
+        self.refresh();
+    }
+
+    /// Reads the screens back from the display server, keeping their pending changes.
+    pub fn refresh(&mut self) {
+        let screens = match self.get_all_screens() {
+            Ok(screens) => screens,
+            Err(e) => {
+                let message = e.to_string();
+                // Avoid stacking the same error on every tick
+                if !self.notifications.iter().any(|n| n.message == message) {
+                    self.notifications.push(Notification {
+                        message,
+                        level: NotificationLevel::Error,
+                        ttl: 8,
+                    });
+                }
+                return;
+            }
+        };
+
+        let is_first_load = self.screens.is_empty();
+        let monitors = identities(&self.screens);
 
         let names = {
             let mut names: Vec<String> = Vec::new();
@@ -48,21 +134,445 @@ impl App {
 
         // Remove unplugged monitors
         for name in names {
+            if self.mode_picker.screen == name {
+                self.mode_picker.close();
+            }
             self.screens.retain(|c| c.name != name);
         }
 
         for screen in screens {
             if let Some(s) = self.screens.iter_mut().find(|c| c.name == screen.name) {
                 s.is_primary = screen.is_primary;
+                s.enabled = screen.enabled;
+                s.rotation = screen.rotation;
+                s.reflection = screen.reflection;
+                s.scale = screen.scale;
                 s.resolution = screen.resolution;
                 s.position = screen.position;
+                s.modes = screen.modes;
+                s.physical_size = screen.physical_size;
+                s.edid = screen.edid;
             } else {
                 self.screens.push(screen);
             }
         }
+
+        if is_first_load {
+            // Start with the first secondary screen placed relative to the primary one
+            self.anchor = self
+                .screens
+                .iter()
+                .position(|s| s.is_primary)
+                .or(self.screens.iter().position(|s| s.enabled))
+                .unwrap_or(0);
+            self.selected = self
+                .screens
+                .iter()
+                .enumerate()
+                .position(|(i, s)| i != self.anchor && !s.is_primary)
+                .unwrap_or(0);
+
+            if self.backend.has_primary()
+                && self.screens.iter().any(|s| s.enabled)
+                && !self.screens.iter().any(|s| s.is_primary)
+            {
+                self.notifications.push(Notification {
+                    message: "No primary output, press P to pick one".to_string(),
+                    level: NotificationLevel::Warning,
+                    ttl: 8,
+                });
+            }
+        }
+
+        self.fix_selection();
+
+        if !is_first_load && identities(&self.screens) != monitors {
+            self.apply_matching_profile();
+        }
+    }
+
+    /// Applies the pending changes, to be confirmed before the timeout.
+    pub fn apply(&mut self) -> AppResult<()> {
+        if self.dry_run {
+            return Ok(());
+        }
+
+        let mut previous = self.screens.clone();
+        previous
+            .iter_mut()
+            .for_each(|screen| screen.clear_pending_changes());
+
+        self.backend.apply(&self.screens)?;
+        self.confirmation = Some(Confirmation::new(previous));
+
+        Ok(())
+    }
+
+    /// Keeps the applied configuration, which stops being pending.
+    pub fn keep_configuration(&mut self) -> AppResult<()> {
+        if self.confirmation.take().is_some() {
+            self.screens
+                .iter_mut()
+                .for_each(|screen| screen.clear_pending_changes());
+            self.refresh();
+            self.backend.keep()?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies back the configuration the screens had before the last apply.
+    ///
+    /// The confirmation is only dropped once reverted, so a failed attempt is
+    /// retried on the next tick.
+    pub fn revert_configuration(&mut self) -> AppResult<()> {
+        let Some(confirmation) = &self.confirmation else {
+            return Ok(());
+        };
+
+        // Compare against what is really applied rather than what was last polled
+        let mut screens = self.backend.get_screens()?;
+        for screen in screens.iter_mut() {
+            if let Some(previous) = confirmation.previous.iter().find(|s| s.name == screen.name) {
+                screen.revert_to(previous);
+            }
+        }
+
+        self.backend.apply(&screens)?;
+
+        self.confirmation = None;
+        self.screens
+            .iter_mut()
+            .for_each(|screen| screen.clear_pending_changes());
+
+        Ok(())
+    }
+
+    /// Restores and applies the saved profile matching the connected monitors, if any.
+    ///
+    /// Unlike manual changes, it is not waiting for a confirmation: the profile is
+    /// known to work and nobody may be in front of the screen when docking.
+    fn apply_matching_profile(&mut self) {
+        if self.dry_run {
+            return;
+        }
+
+        let Some(Ok((profiles, _))) = self.profiles.dir.as_deref().map(profile::load_profiles)
+        else {
+            return;
+        };
+
+        let Some(profile) = profiles.iter().find(|p| p.matches(&self.screens)) else {
+            return;
+        };
+
+        let (message, level) = match profile
+            .restore(&mut self.screens)
+            .and_then(|()| self.backend.apply(&self.screens))
+            .and_then(|()| self.backend.keep())
+        {
+            Ok(()) => {
+                // The profile is live, nothing is left pending
+                self.screens
+                    .iter_mut()
+                    .for_each(|screen| screen.clear_pending_changes());
+                self.refresh();
+                (
+                    format!("Profile {} applied", profile.name),
+                    NotificationLevel::Info,
+                )
+            }
+            Err(e) => (e.to_string(), NotificationLevel::Error),
+        };
+
+        self.notifications.push(Notification {
+            message,
+            level,
+            ttl: 8,
+        });
+    }
+
+    /// Keeps `selected` and `anchor` pointing to existing and distinct screens.
+    fn fix_selection(&mut self) {
+        let nb_screens = self.screens.len();
+
+        if nb_screens == 0 {
+            self.selected = 0;
+            self.anchor = 0;
+            return;
+        }
+
+        if self.selected >= nb_screens {
+            self.selected = nb_screens - 1;
+        }
+
+        if self.anchor >= nb_screens || self.anchor == self.selected {
+            self.anchor = (self.selected + 1) % nb_screens;
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        if self.screens.is_empty() {
+            return;
+        }
+        let previous = self.selected;
+        self.selected = (self.selected + 1) % self.screens.len();
+        if self.anchor == self.selected {
+            self.anchor = previous;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if self.screens.is_empty() {
+            return;
+        }
+        let previous = self.selected;
+        self.selected = (self.selected + self.screens.len() - 1) % self.screens.len();
+        if self.anchor == self.selected {
+            self.anchor = previous;
+        }
+    }
+
+    pub fn cycle_anchor(&mut self) {
+        let nb_screens = self.screens.len();
+        if nb_screens < 2 {
+            return;
+        }
+
+        self.anchor = (self.anchor + 1) % nb_screens;
+        if self.anchor == self.selected {
+            self.anchor = (self.anchor + 1) % nb_screens;
+        }
+    }
+
+    /// Places the selected screen next to the anchor screen.
+    pub fn place(&mut self, location: Location) {
+        place_relative(&mut self.screens, self.selected, self.anchor, location);
+    }
+
+    /// Selects the screen under the cell and starts dragging it.
+    pub fn press_mouse(&mut self, column: u16, row: u16) {
+        let Some(viewport) = self.viewport else {
+            return;
+        };
+
+        // The selected screen is drawn on top of the others
+        let mut order: Vec<usize> = (0..self.screens.len())
+            .filter(|index| self.screens[*index].is_enabled())
+            .collect();
+        order.sort_by_key(|index| *index == self.selected);
+
+        let Some(index) = order.into_iter().rev().find(|index| {
+            ui::screen_rect(&self.screens, &viewport, *index).contains((column, row).into())
+        }) else {
+            return;
+        };
+
+        if index != self.selected {
+            if self.anchor == index {
+                self.anchor = self.selected;
+            }
+            self.selected = index;
+        }
+
+        let (x, y) = viewport.to_layout(column, row);
+        let (screen_x, screen_y) = self.screens[index].pending_position();
+        self.drag = Some(Drag {
+            index,
+            positions: self.screens.iter().map(|s| s.new_position).collect(),
+            viewport,
+            grab: (x - screen_x as f64, y - screen_y as f64),
+            moved: false,
+        });
+    }
+
+    pub fn drag_mouse(&mut self, column: u16, row: u16) {
+        if let Some(drag) = self.drag.as_mut() {
+            drag.moved = true;
+        }
+        self.move_dragged(column, row, None);
+    }
+
+    /// Drops the dragged screen, snapping it to the edges of its neighbours.
+    pub fn release_mouse(&mut self, column: u16, row: u16) {
+        let Some(drag) = &self.drag else {
+            return;
+        };
+
+        if drag.moved {
+            // A cell is tens of pixels wide, snap within a couple of them
+            let distance = (2.0 / drag.viewport.scale).round() as i32;
+            self.move_dragged(
+                column,
+                row,
+                Some((
+                    distance.max(SNAP_DISTANCE),
+                    (2 * distance).max(SNAP_DISTANCE),
+                )),
+            );
+        }
+
+        self.drag = None;
+    }
+
+    fn move_dragged(&mut self, column: u16, row: u16, snap_distance: Option<(i32, i32)>) {
+        let Some(drag) = &self.drag else {
+            return;
+        };
+
+        for (screen, position) in self.screens.iter_mut().zip(drag.positions.iter()) {
+            screen.new_position = *position;
+        }
+
+        let position = drag.position(column, row);
+        screen::move_to(&mut self.screens, drag.index, position, snap_distance);
+    }
+
+    /// Moves the selected screen by `delta` pixels, snapping to its neighbours.
+    pub fn nudge_selected(&mut self, delta: (i32, i32)) {
+        screen::nudge(&mut self.screens, self.selected, delta);
+    }
+
+    /// Cycles the alignment of the selected screen with the anchor.
+    pub fn align_selected(&mut self) {
+        screen::align(&mut self.screens, self.selected, self.anchor);
+    }
+
+    /// Turns the selected screen on or off.
+    ///
+    /// A screen turned on is placed right of the rightmost enabled screen.
+    pub fn toggle_selected(&mut self) -> AppResult<()> {
+        let Some(screen) = self.screens.get(self.selected) else {
+            return Ok(());
+        };
+
+        if screen.is_enabled() {
+            screen::disable(&mut self.screens, self.selected)?;
+        } else {
+            let rightmost = self
+                .screens
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| s.is_enabled())
+                .max_by_key(|(_, s)| s.pending_position().0 as u32 + s.size().0 as u32)
+                .map(|(index, _)| index);
+
+            self.screens[self.selected].new_enabled = Some(true);
+
+            if let Some(rightmost) = rightmost {
+                place_relative(&mut self.screens, self.selected, rightmost, Location::RIGHT);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Makes the selected screen the primary one.
+    pub fn set_primary_selected(&mut self) -> AppResult<()> {
+        let Some(screen) = self.screens.get(self.selected) else {
+            return Ok(());
+        };
+
+        if !self.backend.has_primary() {
+            return Err("The display server has no primary output".into());
+        }
+
+        if !screen.is_enabled() {
+            return Err("Can not make a disabled output primary".into());
+        }
+
+        for (index, screen) in self.screens.iter_mut().enumerate() {
+            screen.new_primary = Some(index == self.selected);
+        }
+
+        Ok(())
+    }
+
+    /// Scales the selected screen up or down by [`screen::SCALE_STEP`].
+    pub fn scale_selected(&mut self, steps: f32) {
+        if let Some(screen) = self.screens.get(self.selected) {
+            let scale = screen.pending_scale() + steps * screen::SCALE_STEP;
+            screen::set_scale(&mut self.screens, self.selected, scale);
+        }
+    }
+
+    pub fn mirror_selected(&mut self) -> AppResult<()> {
+        screen::mirror(&mut self.screens, self.selected, self.anchor)
+    }
+
+    pub fn rotate_selected(&mut self) {
+        if let Some(screen) = self.screens.get_mut(self.selected) {
+            screen.new_rotation = Some(screen.pending_rotation().next());
+        }
+    }
+
+    pub fn reflect_selected(&mut self) {
+        if let Some(screen) = self.screens.get_mut(self.selected) {
+            screen.new_reflection = Some(screen.pending_reflection().next());
+        }
+    }
+
+    /// Opens the mode picker for the selected screen.
+    pub fn open_mode_picker(&mut self) {
+        if let Some(screen) = self.screens.get(self.selected) {
+            if screen.is_enabled() && !screen.modes.is_empty() {
+                self.mode_picker.open(screen);
+            }
+        }
+    }
+
+    /// Sets the mode highlighted in the mode picker as the pending mode of its screen.
+    pub fn pick_mode(&mut self) {
+        if let Some(mode) = self.mode_picker.selected_mode().cloned() {
+            if let Some(screen) = self
+                .screens
+                .iter_mut()
+                .find(|s| s.name == self.mode_picker.screen)
+            {
+                screen.new_mode = Some(mode);
+            }
+        }
+        self.mode_picker.close();
+    }
+
+    /// Saves the current configuration under `name`.
+    pub fn save_profile(&mut self, name: &str) -> AppResult<()> {
+        let profile = Profile::from_screens(name, &self.screens);
+        self.profiles.save(&profile)
+    }
+
+    /// Restores and applies the profile selected in the profiles panel.
+    pub fn restore_selected_profile(&mut self) -> AppResult<()> {
+        let Some(profile) = self.profiles.selected().cloned() else {
+            return Ok(());
+        };
+
+        profile.restore(&mut self.screens)?;
+        self.apply()
     }
 
     pub fn quit(&mut self) {
         self.running = false;
     }
 }
+
+/// The monitors plugged in the screens, sorted so the order does not matter.
+fn identities(screens: &[Screen]) -> Vec<String> {
+    let mut identities: Vec<String> = screens
+        .iter()
+        .map(|screen| screen.fingerprint().unwrap_or(screen.name.clone()))
+        .collect();
+    identities.sort();
+    identities
+}
+
+impl Drag {
+    /// The position of the dragged screen when the cursor is on the cell.
+    fn position(&self, column: u16, row: u16) -> (i32, i32) {
+        let (x, y) = self.viewport.to_layout(column, row);
+        (
+            (x - self.grab.0).round() as i32,
+            (y - self.grab.1).round() as i32,
+        )
+    }
+}
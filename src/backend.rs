@@ -0,0 +1,215 @@
+use std::{fmt::Debug, path::Path, process::Command};
+
+use crate::{
+    app::AppResult,
+    screen::{Mode, Reflection, Rotation, Screen},
+};
+
+/// Declares a backend driving the command line tool `$program`.
+macro_rules! command_backend {
+    ($(#[$meta:meta])* $name:ident, $program:literal) => {
+        $(#[$meta])*
+        #[derive(Debug)]
+        pub struct $name {
+            program: std::path::PathBuf,
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new($program)
+            }
+        }
+
+        impl $name {
+            #[doc = concat!("Uses `program` instead of the `", $program, "` of the `PATH`.")]
+            pub fn new(program: impl Into<std::path::PathBuf>) -> Self {
+                Self {
+                    program: program.into(),
+                }
+            }
+
+            fn run(&self, args: &[String]) -> AppResult<String> {
+                crate::backend::run(&self.program, args)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod fake;
+pub mod hyprland;
+pub mod kscreen;
+pub mod mock;
+pub mod mutter;
+pub mod sway;
+pub mod wlroots;
+pub mod xrandr;
+
+/// A display server interface able to list the outputs and to rearrange them.
+pub trait DisplayBackend: Debug {
+    /// Returns the connected screens with their current configuration.
+    fn get_screens(&self) -> AppResult<Vec<Screen>>;
+
+    /// Applies the pending changes (new positions, locations...) of the screens.
+    fn apply(&self, screens: &[Screen]) -> AppResult<()>;
+
+    /// Describes what [`DisplayBackend::apply`] would run, e.g. a command line.
+    fn preview(&self, screens: &[Screen]) -> String;
+
+    /// Makes the configuration applied last survive a restart, once it is
+    /// confirmed, for display servers applying it only temporarily.
+    fn keep(&self) -> AppResult<()> {
+        Ok(())
+    }
+
+    /// Whether one of the outputs can be primary, as on X11, KScreen and Mutter,
+    /// unlike the wlroots-based compositors.
+    fn has_primary(&self) -> bool {
+        true
+    }
+}
+
+/// Picks the backend of the running display server.
+pub fn detect() -> Box<dyn DisplayBackend> {
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        return Box::new(hyprland::Hyprland::default());
+    }
+
+    if let Some(sway) = sway::Sway::from_env() {
+        return Box::new(sway);
+    }
+
+    // KScreen overrides xrandr on Plasma X11 too
+    if is_desktop("KDE") {
+        return Box::new(kscreen::KScreen::default());
+    }
+
+    if is_desktop("GNOME") {
+        if let Ok(mutter) = mutter::Mutter::session() {
+            return Box::new(mutter);
+        }
+    }
+
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        return Box::new(wlroots::Wlroots::default());
+    }
+
+    Box::new(xrandr::Xrandr::default())
+}
+
+/// Whether `XDG_CURRENT_DESKTOP`, a colon separated list, contains `desktop`.
+fn is_desktop(desktop: &str) -> bool {
+    std::env::var("XDG_CURRENT_DESKTOP")
+        .is_ok_and(|desktops| desktops.split(':').any(|d| d.eq_ignore_ascii_case(desktop)))
+}
+
+/// The names of the `wl_output` transforms, indexed by their value.
+pub const TRANSFORMS: [&str; 8] = [
+    "normal",
+    "90",
+    "180",
+    "270",
+    "flipped",
+    "flipped-90",
+    "flipped-180",
+    "flipped-270",
+];
+
+/// Converts a rotation and a reflection to a `wl_output` transform.
+///
+/// Wayland rotates counter-clockwise like xrandr's `left`, and only flips
+/// around the vertical axis: a `y` reflection is a flip rotated by 180°.
+pub fn transform(rotation: Rotation, reflection: Reflection) -> u8 {
+    let rotation = match rotation {
+        Rotation::Normal => 0,
+        Rotation::Left => 1,
+        Rotation::Inverted => 2,
+        Rotation::Right => 3,
+    };
+
+    match reflection {
+        Reflection::Normal => rotation,
+        Reflection::X => 4 + rotation,
+        Reflection::Y => 4 + (rotation + 2) % 4,
+        Reflection::XY => (rotation + 2) % 4,
+    }
+}
+
+/// Converts a `wl_output` transform back to a rotation and a reflection.
+pub fn from_transform(transform: u8) -> (Rotation, Reflection) {
+    let rotation = match transform % 4 {
+        0 => Rotation::Normal,
+        1 => Rotation::Left,
+        2 => Rotation::Inverted,
+        _ => Rotation::Right,
+    };
+
+    let reflection = if transform % 8 >= 4 {
+        Reflection::X
+    } else {
+        Reflection::Normal
+    };
+
+    (rotation, reflection)
+}
+
+/// Runs `program`, returning what it printed, or the reason it failed.
+pub(crate) fn run(program: &Path, args: &[String]) -> AppResult<String> {
+    let output = Command::new(program).args(args).output()?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The size of the current mode. Disabled outputs would be turned on with
+/// their preferred mode, or else their first one.
+pub(crate) fn resolution(modes: &[Mode]) -> (u16, u16) {
+    modes
+        .iter()
+        .find(|mode| mode.is_current)
+        .or(modes.iter().find(|mode| mode.is_preferred))
+        .or(modes.first())
+        .map_or((0, 0), |mode| (mode.width, mode.height))
+}
+
+/// Quotes an argument for a POSIX shell, when needed.
+pub(crate) fn quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.:/+=x@,".contains(c))
+    {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_transforms_both_ways() {
+        for value in 0..8 {
+            let (rotation, reflection) = from_transform(value);
+            assert_eq!(transform(rotation, reflection), value);
+        }
+
+        assert_eq!(
+            TRANSFORMS[transform(Rotation::Left, Reflection::Normal) as usize],
+            "90"
+        );
+        assert_eq!(
+            TRANSFORMS[transform(Rotation::Normal, Reflection::Y) as usize],
+            "flipped-180"
+        );
+        assert_eq!(
+            TRANSFORMS[transform(Rotation::Right, Reflection::XY) as usize],
+            "90"
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use std::{
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+/// Writes a fake `name` command in `dir`, recording its arguments in `calls`.
+///
+/// When called with `query`, it prints the fixture `tests/fixtures/<fixture>`,
+/// and runs the shell snippet `otherwise` for anything else.
+pub fn program(dir: &Path, name: &str, query: &str, fixture: &str, otherwise: &str) -> PathBuf {
+    let program = dir.join(name);
+    let script = format!(
+        "#!/bin/sh\n\
+         printf '%s\\n' \"$*\" >> '{calls}'\n\
+         if [ \"$1\" = {query} ]; then cat '{fixture}'; else {otherwise}; fi\n",
+        calls = dir.join("calls").display(),
+        fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(fixture)
+            .display(),
+    );
+    fs::write(&program, script).unwrap();
+    fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+    program
+}
+
+/// The arguments of every call of the fake commands of `dir`.
+pub fn calls(dir: &Path) -> Vec<String> {
+    fs::read_to_string(dir.join("calls"))
+        .unwrap_or_default()
+        .lines()
+        .map(String::from)
+        .collect()
+}
@@ -0,0 +1,283 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{
+    app::AppResult,
+    backend::{from_transform, quote, resolution, transform, DisplayBackend},
+    edid,
+    screen::{Location, Mode, Screen},
+};
+
+command_backend!(
+    /// The Hyprland compositor, driven through `hyprctl`.
+    Hyprland,
+    "hyprctl"
+);
+
+impl DisplayBackend for Hyprland {
+    fn get_screens(&self) -> AppResult<Vec<Screen>> {
+        let args = ["monitors", "all", "-j"].map(String::from);
+        let mut screens = screens(&self.run(&args)?)?;
+
+        for screen in screens.iter_mut() {
+            screen.edid = edid::read_sysfs(Path::new("/sys/class/drm"), &screen.name);
+        }
+
+        Ok(screens)
+    }
+
+    fn apply(&self, screens: &[Screen]) -> AppResult<()> {
+        let keywords = keywords(screens);
+        if keywords.is_empty() {
+            return Ok(());
+        }
+
+        let output = self.run(&["--batch".to_string(), keywords.join(" ; ")])?;
+
+        // Every keyword is answered with `ok`, or with what went wrong
+        let errors: Vec<&str> = output
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && *line != "ok")
+            .collect();
+
+        if !errors.is_empty() {
+            return Err(errors.join(", ").into());
+        }
+
+        Ok(())
+    }
+
+    fn preview(&self, screens: &[Screen]) -> String {
+        format!("hyprctl --batch {}", quote(&keywords(screens).join(" ; ")))
+    }
+
+    fn has_primary(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Monitor {
+    name: String,
+    width: u16,
+    height: u16,
+    refresh_rate: f32,
+    x: i32,
+    y: i32,
+    scale: f32,
+    transform: u8,
+    #[serde(default)]
+    disabled: bool,
+    #[serde(default)]
+    available_modes: Vec<String>,
+}
+
+/// Parses a mode of `availableModes`, e.g. `2560x1440@143.97Hz`.
+fn parse_mode(mode: &str) -> Option<Mode> {
+    let (size, refresh) = mode.split_once('@')?;
+    let (width, height) = size.split_once('x')?;
+
+    Some(Mode {
+        name: size.to_string(),
+        width: width.parse().ok()?,
+        height: height.parse().ok()?,
+        refresh: refresh.trim_end_matches("Hz").parse().ok()?,
+        is_current: false,
+        is_preferred: false,
+    })
+}
+
+/// Converts the monitors printed by `hyprctl monitors all -j` to screens.
+pub fn screens(json: &str) -> AppResult<Vec<Screen>> {
+    let monitors: Vec<Monitor> = serde_json::from_str(json)?;
+
+    Ok(monitors
+        .into_iter()
+        .map(|monitor| {
+            let enabled = !monitor.disabled;
+
+            let mut modes: Vec<Mode> = monitor
+                .available_modes
+                .iter()
+                .filter_map(|mode| parse_mode(mode))
+                .collect();
+
+            if enabled {
+                if let Some(mode) = modes.iter_mut().find(|mode| {
+                    mode.width == monitor.width
+                        && mode.height == monitor.height
+                        && (mode.refresh - monitor.refresh_rate).abs() < 0.01
+                }) {
+                    mode.is_current = true;
+                }
+            }
+
+            // Hyprland does not tell which mode is the preferred one
+            let resolution = if enabled {
+                (monitor.width, monitor.height)
+            } else {
+                resolution(&modes)
+            };
+
+            let (rotation, reflection) = from_transform(monitor.transform);
+
+            Screen {
+                name: monitor.name,
+                enabled,
+                resolution,
+                position: (monitor.x.max(0) as u16, monitor.y.max(0) as u16),
+                modes,
+                rotation,
+                reflection,
+                scale: if monitor.scale > 0.0 {
+                    monitor.scale
+                } else {
+                    1.0
+                },
+                ..Default::default()
+            }
+        })
+        .collect())
+}
+
+/// Builds the `monitor` keywords applying the pending changes of the screens.
+pub fn keywords(screens: &[Screen]) -> Vec<String> {
+    screens
+        .iter()
+        .filter(|screen| screen.has_pending_changes())
+        .map(|screen| {
+            if !screen.is_enabled() {
+                return format!("keyword monitor {},disable", screen.name);
+            }
+
+            let mode = match screen.mode() {
+                Some(mode) => format!("{}x{}@{:.2}", mode.width, mode.height, mode.refresh),
+                None => "preferred".to_string(),
+            };
+
+            let (x, y) = screen.pending_position();
+            let mut keyword = format!(
+                "keyword monitor {},{},{}x{},{}",
+                screen.name,
+                mode,
+                x,
+                y,
+                screen.pending_scale()
+            );
+
+            let transform = transform(screen.pending_rotation(), screen.pending_reflection());
+            if transform != 0 {
+                keyword.push_str(&format!(",transform,{}", transform));
+            }
+
+            if let (Some(Location::SAME), Some(source)) = (&screen.location, &screen.relative_to) {
+                keyword.push_str(&format!(",mirror,{}", source));
+            }
+
+            keyword
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::fake::{self, calls},
+        screen::{self, Reflection, Rotation},
+    };
+
+    const LAPTOP_DOCKED: &str = include_str!("../../tests/fixtures/hyprland/laptop-docked.json");
+
+    /// A fake `hyprctl` answering the batches with `reply`.
+    fn fake_hyprctl(dir: &Path, reply: &str) -> Hyprland {
+        Hyprland::new(fake::program(
+            dir,
+            "hyprctl",
+            "monitors",
+            "hyprland/laptop-docked.json",
+            &format!("printf '{}'", reply),
+        ))
+    }
+
+    #[test]
+    fn converts_monitors_to_screens() {
+        let screens = screens(LAPTOP_DOCKED).unwrap();
+
+        assert_eq!(screens.len(), 3);
+
+        let edp = &screens[0];
+        assert_eq!(edp.size(), (1920, 1080));
+        assert_eq!(edp.modes.len(), 3);
+        assert_eq!(edp.mode().unwrap().name, "3840x2160");
+
+        let dp = &screens[1];
+        assert_eq!(dp.position, (1920, 0));
+        assert_eq!(dp.rotation, Rotation::Right);
+        assert_eq!(dp.reflection, Reflection::Normal);
+        assert_eq!(dp.size(), (1440, 2560));
+        assert_eq!(dp.mode().unwrap().refresh, 59.95);
+
+        let hdmi = &screens[2];
+        assert!(!hdmi.enabled);
+        assert_eq!(hdmi.resolution, (1920, 1080));
+        assert!(hdmi.mode().is_none());
+    }
+
+    #[test]
+    fn builds_the_keywords_of_the_pending_changes() {
+        let mut screens = screens(LAPTOP_DOCKED).unwrap();
+        screens[1].new_enabled = Some(false);
+        screens[2].new_enabled = Some(true);
+        screens[2].new_reflection = Some(Reflection::X);
+        screen::mirror(&mut screens, 2, 0).unwrap();
+
+        assert_eq!(
+            keywords(&screens),
+            vec![
+                "keyword monitor DP-2,disable",
+                "keyword monitor HDMI-A-1,1920x1080@60.00,0x0,1,transform,4,mirror,eDP-1",
+            ]
+        );
+    }
+
+    #[test]
+    fn applies_the_keywords_in_a_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = fake_hyprctl(dir.path(), "ok\\n\\nok\\n");
+
+        let mut screens = backend.get_screens().unwrap();
+        screens[1].new_position = Some((3840, 0));
+        screens[2].new_enabled = Some(true);
+        screens[2].new_position = Some((0, 1080));
+        backend.apply(&screens).unwrap();
+
+        let batch = "keyword monitor DP-2,2560x1440@59.95,3840x0,1,transform,3 ; \
+                     keyword monitor HDMI-A-1,preferred,0x1080,1";
+        assert_eq!(
+            calls(dir.path()),
+            vec!["monitors all -j".to_string(), format!("--batch {}", batch)]
+        );
+        assert_eq!(
+            backend.preview(&screens),
+            format!("hyprctl --batch '{}'", batch)
+        );
+    }
+
+    #[test]
+    fn reports_the_rejected_keywords() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = fake_hyprctl(dir.path(), "invalid resolution\\n");
+
+        let mut screens = backend.get_screens().unwrap();
+        screens[0].new_scale = Some(1.5);
+
+        assert_eq!(
+            backend.apply(&screens).unwrap_err().to_string(),
+            "invalid resolution"
+        );
+    }
+}
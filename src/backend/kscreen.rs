@@ -0,0 +1,301 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{
+    app::AppResult,
+    backend::{quote, resolution, DisplayBackend},
+    edid,
+    screen::{Location, Mode, Reflection, Rotation, Screen},
+};
+
+command_backend!(
+    /// KDE Plasma, on X11 or Wayland, driven through `kscreen-doctor` so the
+    /// changes are saved by KScreen instead of being overridden by it.
+    KScreen,
+    "kscreen-doctor"
+);
+
+impl DisplayBackend for KScreen {
+    fn get_screens(&self) -> AppResult<Vec<Screen>> {
+        let mut screens = screens(&self.run(&["-j".to_string()])?)?;
+
+        for screen in screens.iter_mut() {
+            screen.edid = edid::read_sysfs(Path::new("/sys/class/drm"), &screen.name);
+        }
+
+        Ok(screens)
+    }
+
+    fn apply(&self, screens: &[Screen]) -> AppResult<()> {
+        if screens
+            .iter()
+            .any(|screen| screen.pending_reflection() != Reflection::Normal)
+        {
+            return Err("KScreen can not reflect outputs".into());
+        }
+
+        let args = arguments(screens);
+        if args.is_empty() {
+            return Ok(());
+        }
+
+        self.run(&args)?;
+        Ok(())
+    }
+
+    fn preview(&self, screens: &[Screen]) -> String {
+        let mut command = vec!["kscreen-doctor".to_string()];
+        command.extend(arguments(screens).iter().map(|arg| quote(arg)));
+        command.join(" ")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Config {
+    outputs: Vec<Output>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Output {
+    name: String,
+    connected: bool,
+    enabled: bool,
+    #[serde(default)]
+    current_mode_id: String,
+    #[serde(default)]
+    preferred_modes: Vec<String>,
+    modes: Vec<OutputMode>,
+    pos: Position,
+    rotation: u8,
+    scale: f32,
+    size_m_m: Option<Size<u32>>,
+    /// Plasma 5.
+    primary: Option<bool>,
+    /// Plasma 6, 1 being the primary output.
+    priority: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OutputMode {
+    id: String,
+    refresh_rate: f32,
+    size: Size<u16>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Position {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct Size<T> {
+    width: T,
+    height: T,
+}
+
+/// Converts the outputs printed by `kscreen-doctor -j` to screens.
+pub fn screens(json: &str) -> AppResult<Vec<Screen>> {
+    let config: Config = serde_json::from_str(json)?;
+
+    Ok(config
+        .outputs
+        .into_iter()
+        .filter(|output| output.connected)
+        .map(|output| {
+            let modes: Vec<Mode> = output
+                .modes
+                .iter()
+                .map(|mode| Mode {
+                    name: format!("{}x{}", mode.size.width, mode.size.height),
+                    width: mode.size.width,
+                    height: mode.size.height,
+                    refresh: mode.refresh_rate,
+                    is_current: output.enabled && mode.id == output.current_mode_id,
+                    is_preferred: output.preferred_modes.contains(&mode.id),
+                })
+                .collect();
+
+            let rotation = match output.rotation {
+                2 => Rotation::Left,
+                4 => Rotation::Inverted,
+                8 => Rotation::Right,
+                _ => Rotation::Normal,
+            };
+
+            Screen {
+                name: output.name,
+                is_primary: output.enabled
+                    && (output.priority == Some(1) || output.primary == Some(true)),
+                enabled: output.enabled,
+                resolution: resolution(&modes),
+                position: (output.pos.x.max(0) as u16, output.pos.y.max(0) as u16),
+                modes,
+                physical_size: output
+                    .size_m_m
+                    .filter(|size| size.width > 0 && size.height > 0)
+                    .map(|size| (size.width, size.height)),
+                rotation,
+                scale: if output.scale > 0.0 {
+                    output.scale
+                } else {
+                    1.0
+                },
+                ..Default::default()
+            }
+        })
+        .collect())
+}
+
+/// Builds the kscreen-doctor arguments applying the pending changes of the screens.
+pub fn arguments(screens: &[Screen]) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+
+    for screen in screens.iter().filter(|screen| screen.has_pending_changes()) {
+        let output = format!("output.{}", screen.name);
+
+        if !screen.is_enabled() {
+            args.push(format!("{}.disable", output));
+            continue;
+        }
+
+        args.push(format!("{}.enable", output));
+
+        // KScreen names the modes after their rounded refresh rate
+        if let Some(mode) = screen.mode() {
+            args.push(format!(
+                "{}.mode.{}x{}@{}",
+                output,
+                mode.width,
+                mode.height,
+                mode.refresh.round()
+            ));
+        }
+
+        let (x, y) = screen.pending_position();
+        let rotation = match screen.pending_rotation() {
+            Rotation::Normal => "none",
+            Rotation::Left => "left",
+            Rotation::Inverted => "inverted",
+            Rotation::Right => "right",
+        };
+        args.extend([
+            format!("{}.position.{},{}", output, x, y),
+            format!("{}.rotation.{}", output, rotation),
+            format!("{}.scale.{}", output, screen.pending_scale()),
+        ]);
+
+        if let (Some(Location::SAME), Some(source)) = (&screen.location, &screen.relative_to) {
+            args.push(format!("{}.mirror.{}", output, source));
+        }
+
+        if screen.pending_primary() && !screen.is_primary {
+            args.push(format!("{}.primary", output));
+        }
+    }
+
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{self, calls};
+
+    const LAPTOP_DOCKED: &str = include_str!("../../tests/fixtures/kscreen/laptop-docked.json");
+
+    fn fake_kscreen_doctor(dir: &Path) -> KScreen {
+        KScreen::new(fake::program(
+            dir,
+            "kscreen-doctor",
+            "-j",
+            "kscreen/laptop-docked.json",
+            ":",
+        ))
+    }
+
+    #[test]
+    fn converts_connected_outputs_to_screens() {
+        let screens = screens(LAPTOP_DOCKED).unwrap();
+
+        assert_eq!(screens.len(), 3);
+
+        let edp = &screens[0];
+        assert!(edp.is_primary);
+        assert_eq!(edp.size(), (1920, 1080));
+        assert_eq!(edp.physical_size, Some((310, 170)));
+        assert!(edp.modes[0].is_preferred);
+
+        let dp = &screens[1];
+        assert!(!dp.is_primary);
+        assert_eq!(dp.position, (1920, 0));
+        assert_eq!(dp.rotation, Rotation::Right);
+        assert_eq!(dp.size(), (1440, 2560));
+        assert_eq!(dp.mode().unwrap().refresh, 143.972);
+
+        let hdmi = &screens[2];
+        assert!(!hdmi.enabled);
+        assert_eq!(hdmi.resolution, (1920, 1080));
+        assert!(hdmi.mode().is_none());
+    }
+
+    #[test]
+    fn applies_the_pending_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = fake_kscreen_doctor(dir.path());
+
+        let mut screens = backend.get_screens().unwrap();
+        screens[0].new_primary = Some(false);
+        screens[1].new_primary = Some(true);
+        screens[1].new_mode = Some(screens[1].modes[0].clone());
+        screens[2].new_enabled = Some(false);
+        backend.apply(&screens).unwrap();
+
+        let args = "output.eDP-1.enable output.eDP-1.mode.3840x2160@60 \
+                    output.eDP-1.position.0,0 output.eDP-1.rotation.none output.eDP-1.scale.2 \
+                    output.DP-2.enable output.DP-2.mode.2560x1440@60 \
+                    output.DP-2.position.1920,0 output.DP-2.rotation.right output.DP-2.scale.1 \
+                    output.DP-2.primary";
+        assert_eq!(calls(dir.path()), vec!["-j".to_string(), args.to_string()]);
+        assert_eq!(
+            backend.preview(&screens),
+            format!("kscreen-doctor {}", args)
+        );
+    }
+
+    #[test]
+    fn mirrors_and_disables_outputs() {
+        let mut screens = screens(LAPTOP_DOCKED).unwrap();
+        screens[1].new_enabled = Some(false);
+        screens[2].new_enabled = Some(true);
+        crate::screen::mirror(&mut screens, 2, 0).unwrap();
+
+        assert_eq!(
+            arguments(&screens),
+            vec![
+                "output.DP-2.disable",
+                "output.HDMI-A-1.enable",
+                "output.HDMI-A-1.mode.1920x1080@60",
+                "output.HDMI-A-1.position.0,0",
+                "output.HDMI-A-1.rotation.none",
+                "output.HDMI-A-1.scale.1",
+                "output.HDMI-A-1.mirror.eDP-1",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_reflections() {
+        let backend = KScreen::new("/nonexistent/kscreen-doctor");
+        let mut screens = screens(LAPTOP_DOCKED).unwrap();
+        screens[0].new_reflection = Some(Reflection::X);
+
+        assert_eq!(
+            backend.apply(&screens).unwrap_err().to_string(),
+            "KScreen can not reflect outputs"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{app::AppResult, backend::DisplayBackend, screen::Screen};
+
+/// In-memory backend, used to exercise the app without a display server.
+///
+/// Clones share the same state, so a test can keep a handle on the backend
+/// given to the [`App`](crate::app::App) and inspect what was applied.
+#[derive(Debug, Default, Clone)]
+pub struct MockBackend {
+    screens: Arc<Mutex<Vec<Screen>>>,
+    applied: Arc<Mutex<Vec<Vec<Screen>>>>,
+    failure: Arc<Mutex<Option<String>>>,
+}
+
+impl MockBackend {
+    pub fn new(screens: Vec<Screen>) -> Self {
+        Self {
+            screens: Arc::new(Mutex::new(screens)),
+            applied: Arc::new(Mutex::new(Vec::new())),
+            failure: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Makes every call fail with `error`, until it is set back to `None`.
+    pub fn set_failure(&self, error: Option<&str>) {
+        *self.failure.lock().unwrap() = error.map(String::from);
+    }
+
+    fn check(&self) -> AppResult<()> {
+        match self.failure.lock().unwrap().as_ref() {
+            Some(error) => Err(error.clone().into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the connected screens, e.g. to simulate a hotplug.
+    pub fn set_screens(&self, screens: Vec<Screen>) {
+        *self.screens.lock().unwrap() = screens;
+    }
+
+    /// Returns every configuration passed to [`DisplayBackend::apply`], oldest first.
+    pub fn applied(&self) -> Vec<Vec<Screen>> {
+        self.applied.lock().unwrap().clone()
+    }
+}
+
+impl DisplayBackend for MockBackend {
+    fn get_screens(&self) -> AppResult<Vec<Screen>> {
+        self.check()?;
+        Ok(self.screens.lock().unwrap().clone())
+    }
+
+    fn apply(&self, screens: &[Screen]) -> AppResult<()> {
+        self.check()?;
+        self.applied.lock().unwrap().push(screens.to_vec());
+
+        for screen in self.screens.lock().unwrap().iter_mut() {
+            if let Some(pending) = screens.iter().find(|s| s.name == screen.name) {
+                screen.position = pending.pending_position();
+                screen.enabled = pending.is_enabled();
+                screen.rotation = pending.pending_rotation();
+                screen.reflection = pending.pending_reflection();
+                screen.is_primary = pending.pending_primary();
+                screen.scale = pending.pending_scale();
+
+                if let Some(mode) = &pending.new_mode {
+                    screen.resolution = (mode.width, mode.height);
+                    screen.modes.iter_mut().for_each(|m| {
+                        m.is_current = m.name == mode.name && m.refresh == mode.refresh
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn preview(&self, screens: &[Screen]) -> String {
+        let names: Vec<&str> = screens
+            .iter()
+            .filter(|screen| screen.has_pending_changes())
+            .map(|screen| screen.name.as_str())
+            .collect();
+
+        format!("mock apply {}", names.join(" "))
+    }
+}
@@ -0,0 +1,704 @@
+use std::{
+    cell::{Ref, RefCell},
+    collections::HashMap,
+    path::Path,
+};
+
+use zbus::{
+    blocking::{connection::Builder, Connection, Proxy},
+    zvariant::OwnedValue,
+};
+
+use crate::{
+    app::AppResult,
+    backend::{from_transform, quote, resolution, transform, DisplayBackend},
+    edid,
+    screen::{self, Mode, Screen},
+};
+
+const DESTINATION: &str = "org.gnome.Mutter.DisplayConfig";
+const PATH: &str = "/org/gnome/Mutter/DisplayConfig";
+const INTERFACE: &str = "org.gnome.Mutter.DisplayConfig";
+
+/// The methods of `ApplyMonitorsConfig`.
+const VERIFY: u32 = 0;
+const TEMPORARY: u32 = 1;
+const PERSISTENT: u32 = 2;
+
+/// The `layout-mode` in which the logical monitors are laid out in physical
+/// pixels, whatever their scale, rather than in logical ones.
+const PHYSICAL: u32 = 2;
+
+type Properties = HashMap<String, OwnedValue>;
+
+/// Connector, vendor, product and serial.
+pub type MonitorSpec = (String, String, String, String);
+
+/// Id, width, height, refresh rate, preferred scale, supported scales and properties.
+pub type MonitorMode = (String, i32, i32, f64, f64, Vec<f64>, Properties);
+
+pub type Monitor = (MonitorSpec, Vec<MonitorMode>, Properties);
+
+/// x, y, scale, transform, primary, monitors and properties.
+pub type LogicalMonitor = (i32, i32, f64, u32, bool, Vec<MonitorSpec>, Properties);
+
+/// The reply of `GetCurrentState`: serial, monitors, logical monitors and properties.
+pub type State = (u32, Vec<Monitor>, Vec<LogicalMonitor>, Properties);
+
+/// x, y, scale, transform, primary and `(connector, mode id, properties)` of the monitors.
+pub type LogicalMonitorConfig = (i32, i32, f64, u32, bool, Vec<(String, String, Properties)>);
+
+/// GNOME, on X11 or Wayland, through the `org.gnome.Mutter.DisplayConfig`
+/// D-Bus interface.
+///
+/// Configurations are verified, then applied temporarily until they are
+/// kept, so Mutter only saves the confirmed ones.
+#[derive(Debug)]
+pub struct Mutter {
+    connection: Connection,
+    /// The state read last, which the previews are built from.
+    state: RefCell<Option<State>>,
+}
+
+impl Mutter {
+    pub fn new(connection: Connection) -> Self {
+        Self {
+            connection,
+            state: RefCell::new(None),
+        }
+    }
+
+    /// Connects to Mutter on the session bus.
+    pub fn session() -> AppResult<Self> {
+        Ok(Self::new(Connection::session()?))
+    }
+
+    /// Connects to Mutter on the bus at `address`.
+    pub fn connect(address: &str) -> AppResult<Self> {
+        Ok(Self::new(Builder::address(address)?.build()?))
+    }
+
+    fn proxy(&self) -> AppResult<Proxy<'_>> {
+        Ok(Proxy::new(&self.connection, DESTINATION, PATH, INTERFACE)?)
+    }
+
+    fn current_state(&self) -> AppResult<Ref<'_, State>> {
+        let state: State = self.proxy()?.call("GetCurrentState", &())?;
+        *self.state.borrow_mut() = Some(state);
+        self.cached_state()
+    }
+
+    /// The state read last, only asking Mutter the first time.
+    fn cached_state(&self) -> AppResult<Ref<'_, State>> {
+        if self.state.borrow().is_none() {
+            return self.current_state();
+        }
+        Ok(Ref::map(self.state.borrow(), |state| {
+            state.as_ref().unwrap()
+        }))
+    }
+
+    fn apply_with(&self, screens: &[Screen], method: u32) -> AppResult<()> {
+        let state = self.current_state()?;
+        let logical_monitors = logical_monitors(screens, &state)?;
+
+        self.proxy()?.call::<_, _, ()>(
+            "ApplyMonitorsConfig",
+            &(state.0, method, &logical_monitors, Properties::new()),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl DisplayBackend for Mutter {
+    fn get_screens(&self) -> AppResult<Vec<Screen>> {
+        let mut screens = screens(&*self.current_state()?);
+
+        for screen in screens.iter_mut() {
+            screen.edid = edid::read_sysfs(Path::new("/sys/class/drm"), &screen.name);
+        }
+
+        Ok(screens)
+    }
+
+    fn apply(&self, screens: &[Screen]) -> AppResult<()> {
+        self.apply_with(screens, VERIFY)
+            .map_err(|e| format!("Mutter rejected the configuration: {}", e))?;
+
+        self.apply_with(screens, TEMPORARY)
+    }
+
+    fn keep(&self) -> AppResult<()> {
+        let screens = screens(&*self.current_state()?);
+        self.apply_with(&screens, PERSISTENT)
+    }
+
+    /// The equivalent `gdbus` call, the serial and the mode ids coming from Mutter.
+    ///
+    /// It is built from the state read with the screens, as it is drawn on
+    /// every frame.
+    fn preview(&self, screens: &[Screen]) -> String {
+        let (serial, logical_monitors) = match self
+            .cached_state()
+            .and_then(|state| Ok((state.0, logical_monitors(screens, &state)?)))
+        {
+            Ok(config) => config,
+            Err(e) => return format!("ApplyMonitorsConfig: {}", e),
+        };
+
+        let logical_monitors: Vec<String> = logical_monitors
+            .iter()
+            .map(|(x, y, scale, transform, primary, monitors)| {
+                let monitors: Vec<String> = monitors
+                    .iter()
+                    .map(|(connector, mode, _)| {
+                        format!("(\"{}\", \"{}\", @a{{sv}} {{}})", connector, mode)
+                    })
+                    .collect();
+                format!(
+                    "({}, {}, {:?}, uint32 {}, {}, [{}])",
+                    x,
+                    y,
+                    scale,
+                    transform,
+                    primary,
+                    monitors.join(", ")
+                )
+            })
+            .collect();
+
+        [
+            "gdbus call --session".to_string(),
+            format!("--dest {} --object-path {}", DESTINATION, PATH),
+            format!("--method {}.ApplyMonitorsConfig", INTERFACE),
+            format!("{} {}", serial, TEMPORARY),
+            quote(&format!("[{}]", logical_monitors.join(", "))),
+            quote("{}"),
+        ]
+        .join(" ")
+    }
+}
+
+fn flag(properties: &Properties, name: &str) -> bool {
+    properties
+        .get(name)
+        .and_then(|value| bool::try_from(value).ok())
+        .unwrap_or(false)
+}
+
+fn is_physical(properties: &Properties) -> bool {
+    properties
+        .get("layout-mode")
+        .and_then(|value| u32::try_from(value).ok())
+        == Some(PHYSICAL)
+}
+
+/// Converts the monitors of a `GetCurrentState` reply to screens, the ones
+/// outside of any logical monitor being disabled.
+///
+/// In the physical layout mode, the scale does not change the room taken by
+/// the monitors, so the screens are left unscaled.
+pub fn screens(state: &State) -> Vec<Screen> {
+    let (_, monitors, logical_monitors, properties) = state;
+    let physical = is_physical(properties);
+
+    monitors
+        .iter()
+        .map(|((connector, _, _, _), modes, _)| {
+            let modes: Vec<Mode> = modes
+                .iter()
+                .map(|(_, width, height, refresh, _, _, properties)| Mode {
+                    name: format!("{}x{}", width, height),
+                    width: *width as u16,
+                    height: *height as u16,
+                    refresh: *refresh as f32,
+                    is_current: flag(properties, "is-current"),
+                    is_preferred: flag(properties, "is-preferred"),
+                })
+                .collect();
+
+            let logical_monitor = logical_monitors
+                .iter()
+                .find(|(.., specs, _)| specs.iter().any(|spec| &spec.0 == connector));
+
+            let mut screen = Screen {
+                name: connector.clone(),
+                resolution: resolution(&modes),
+                modes,
+                ..Default::default()
+            };
+
+            if let Some((x, y, scale, transform, primary, specs, _)) = logical_monitor {
+                let (rotation, reflection) = from_transform(*transform as u8);
+                screen.enabled = true;
+                screen.position = ((*x).max(0) as u16, (*y).max(0) as u16);
+                screen.scale = if physical { 1.0 } else { *scale as f32 };
+                screen.rotation = rotation;
+                screen.reflection = reflection;
+                // The other monitors of the logical monitor mirror the first one
+                screen.is_primary = *primary && &specs[0].0 == connector;
+            }
+
+            screen
+        })
+        .collect()
+}
+
+/// Builds the logical monitors of the screens, a mirror joining the logical
+/// monitor of the screen it mirrors.
+///
+/// In the physical layout mode, the monitors keep the scale they have in
+/// `state`, the screens being unscaled.
+pub fn logical_monitors(screens: &[Screen], state: &State) -> AppResult<Vec<LogicalMonitorConfig>> {
+    let (_, monitors, current, properties) = state;
+    let physical = is_physical(properties);
+    let mut logical_monitors: Vec<LogicalMonitorConfig> = Vec::new();
+    let mut owners: Vec<usize> = Vec::new();
+
+    for (index, screen) in screens.iter().enumerate() {
+        if !screen.is_enabled() {
+            continue;
+        }
+
+        let (_, modes, _) = monitors
+            .iter()
+            .find(|((connector, ..), ..)| *connector == screen.name)
+            .ok_or(format!("Output {} not found", screen.name))?;
+
+        let mode = match screen.mode() {
+            Some(mode) => modes.iter().find(|(_, width, height, refresh, ..)| {
+                *width == mode.width as i32
+                    && *height == mode.height as i32
+                    && (*refresh as f32 - mode.refresh).abs() < 0.01
+            }),
+            None => modes
+                .iter()
+                .find(|(.., properties)| flag(properties, "is-preferred")),
+        }
+        .ok_or(format!("{} does not support the mode", screen.name))?;
+
+        let monitor = (screen.name.clone(), mode.0.clone(), Properties::new());
+
+        let source = screen::mirrored(screens, index).first().copied();
+        if let Some(logical_monitor) = source
+            .and_then(|source| owners.iter().position(|owner| *owner == source))
+            .and_then(|position| logical_monitors.get_mut(position))
+        {
+            logical_monitor.5.push(monitor);
+            continue;
+        }
+
+        let scale = if physical {
+            current
+                .iter()
+                .find(|(.., specs, _)| specs.iter().any(|spec| spec.0 == screen.name))
+                .map_or(1.0, |(_, _, scale, ..)| *scale)
+        } else {
+            screen.pending_scale() as f64
+        };
+
+        let (x, y) = screen.pending_position();
+        logical_monitors.push((
+            x as i32,
+            y as i32,
+            scale,
+            transform(screen.pending_rotation(), screen.pending_reflection()) as u32,
+            screen.pending_primary(),
+            vec![monitor],
+        ));
+        owners.push(index);
+    }
+
+    // Mutter wants exactly one primary logical monitor
+    if !logical_monitors
+        .iter()
+        .any(|logical_monitor| logical_monitor.4)
+    {
+        if let Some(logical_monitor) = logical_monitors.first_mut() {
+            logical_monitor.4 = true;
+        }
+    }
+
+    Ok(logical_monitors)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    use zbus::{fdo, interface, zvariant::Value};
+
+    use super::*;
+    use crate::screen::{Reflection, Rotation};
+
+    type Applied = Arc<Mutex<Vec<(u32, Vec<LogicalMonitorConfig>)>>>;
+
+    /// A stub of the Mutter DisplayConfig interface, serving a docked laptop.
+    struct DisplayConfig {
+        applied: Applied,
+        reads: Arc<AtomicUsize>,
+        reject: bool,
+    }
+
+    fn properties(flags: &[(&str, bool)]) -> Properties {
+        flags
+            .iter()
+            .map(|(name, value)| (name.to_string(), Value::from(*value).try_into().unwrap()))
+            .collect()
+    }
+
+    fn spec(connector: &str) -> MonitorSpec {
+        (
+            connector.to_string(),
+            "DEL".to_string(),
+            "DELL U2719D".to_string(),
+            "5KC0F03".to_string(),
+        )
+    }
+
+    fn mode(width: i32, height: i32, refresh: f64, current: bool, preferred: bool) -> MonitorMode {
+        (
+            format!("{}x{}@{:.3}", width, height, refresh),
+            width,
+            height,
+            refresh,
+            1.0,
+            vec![1.0, 2.0],
+            properties(&[("is-current", current), ("is-preferred", preferred)]),
+        )
+    }
+
+    fn laptop_docked() -> State {
+        (
+            7,
+            vec![
+                (
+                    spec("eDP-1"),
+                    vec![
+                        mode(3840, 2160, 60.0, true, true),
+                        mode(1920, 1080, 60.0, false, false),
+                    ],
+                    Properties::new(),
+                ),
+                (
+                    spec("DP-2"),
+                    vec![
+                        mode(2560, 1440, 59.951, true, true),
+                        mode(1920, 1080, 60.0, false, false),
+                    ],
+                    Properties::new(),
+                ),
+                (
+                    spec("HDMI-1"),
+                    vec![mode(1920, 1080, 60.0, false, true)],
+                    Properties::new(),
+                ),
+            ],
+            vec![
+                (0, 0, 2.0, 0, true, vec![spec("eDP-1")], Properties::new()),
+                (
+                    1920,
+                    0,
+                    1.0,
+                    3,
+                    false,
+                    vec![spec("DP-2")],
+                    Properties::new(),
+                ),
+            ],
+            layout_mode(1),
+        )
+    }
+
+    fn layout_mode(mode: u32) -> Properties {
+        Properties::from([(
+            "layout-mode".to_string(),
+            Value::from(mode).try_into().unwrap(),
+        )])
+    }
+
+    /// The same monitors, laid out in physical pixels.
+    fn laptop_docked_physical() -> State {
+        let (serial, monitors, mut logical_monitors, _) = laptop_docked();
+        logical_monitors[1].0 = 3840;
+        (serial, monitors, logical_monitors, layout_mode(PHYSICAL))
+    }
+
+    #[interface(name = "org.gnome.Mutter.DisplayConfig")]
+    impl DisplayConfig {
+        fn get_current_state(&self) -> State {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            laptop_docked()
+        }
+
+        fn apply_monitors_config(
+            &self,
+            serial: u32,
+            method: u32,
+            logical_monitors: Vec<LogicalMonitorConfig>,
+            _properties: Properties,
+        ) -> fdo::Result<()> {
+            if serial != 7 {
+                return Err(fdo::Error::AccessDenied("Wrong serial".to_string()));
+            }
+            if self.reject && method == VERIFY {
+                return Err(fdo::Error::InvalidArgs("Invalid mode".to_string()));
+            }
+            self.applied
+                .lock()
+                .unwrap()
+                .push((method, logical_monitors));
+            Ok(())
+        }
+    }
+
+    /// A private session bus, stopped when dropped.
+    struct Bus {
+        daemon: Child,
+        address: String,
+        _dir: tempfile::TempDir,
+    }
+
+    impl Bus {
+        /// Starts a `dbus-daemon`, if it is installed.
+        fn start() -> Option<Self> {
+            let dir = tempfile::tempdir().unwrap();
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .arg(format!(
+                    "--address=unix:path={}",
+                    dir.path().join("bus").display()
+                ))
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+
+            // Printed once the bus is listening
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take().unwrap())
+                .read_line(&mut address)
+                .unwrap();
+
+            Some(Self {
+                daemon,
+                address: address.trim().to_string(),
+                _dir: dir,
+            })
+        }
+
+        fn mutter(&self) -> Mutter {
+            Mutter::connect(&self.address).unwrap()
+        }
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    /// A backend talking to a stub owning the name of Mutter on a private bus.
+    struct Stub {
+        backend: Mutter,
+        applied: Applied,
+        /// How many times the state was read.
+        reads: Arc<AtomicUsize>,
+        _server: Connection,
+        _bus: Bus,
+    }
+
+    fn stub(reject: bool) -> Option<Stub> {
+        let Some(bus) = Bus::start() else {
+            eprintln!("dbus-daemon is not installed, skipping");
+            return None;
+        };
+        let applied = Applied::default();
+        let reads = Arc::<AtomicUsize>::default();
+
+        let server = Builder::address(bus.address.as_str())
+            .unwrap()
+            .name(DESTINATION)
+            .unwrap()
+            .serve_at(
+                PATH,
+                DisplayConfig {
+                    applied: applied.clone(),
+                    reads: reads.clone(),
+                    reject,
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+
+        Some(Stub {
+            backend: bus.mutter(),
+            applied,
+            reads,
+            _server: server,
+            _bus: bus,
+        })
+    }
+
+    #[test]
+    fn converts_the_current_state_to_screens() {
+        let screens = screens(&laptop_docked());
+
+        assert_eq!(screens.len(), 3);
+
+        let edp = &screens[0];
+        assert!(edp.enabled && edp.is_primary);
+        assert_eq!(edp.scale, 2.0);
+        assert_eq!(edp.size(), (1920, 1080));
+
+        let dp = &screens[1];
+        assert_eq!(dp.position, (1920, 0));
+        assert_eq!(dp.rotation, Rotation::Right);
+        assert_eq!(dp.reflection, Reflection::Normal);
+        assert_eq!(dp.size(), (1440, 2560));
+
+        let hdmi = &screens[2];
+        assert!(!hdmi.enabled);
+        assert_eq!(hdmi.resolution, (1920, 1080));
+    }
+
+    #[test]
+    fn sizes_physical_layouts_without_the_scale() {
+        let state = laptop_docked_physical();
+        let mut screens = screens(&state);
+
+        let edp = &screens[0];
+        assert_eq!(edp.scale, 1.0);
+        assert_eq!(edp.size(), (3840, 2160));
+
+        let dp = &screens[1];
+        assert_eq!(dp.position, (3840, 0));
+        assert_eq!(dp.size(), (1440, 2560));
+
+        screens[1].new_position = Some((0, 2160));
+        let logical_monitors = logical_monitors(&screens, &state).unwrap();
+        let layout: Vec<(i32, i32, f64)> = logical_monitors
+            .iter()
+            .map(|(x, y, scale, ..)| (*x, *y, *scale))
+            .collect();
+        assert_eq!(layout, vec![(0, 0, 2.0), (0, 2160, 1.0)]);
+    }
+
+    #[test]
+    fn mirrors_share_a_logical_monitor() {
+        let state = laptop_docked();
+        let mut screens = screens(&state);
+        screens[1].new_enabled = Some(false);
+        screens[2].new_enabled = Some(true);
+        screens[0].new_mode = Some(screens[0].modes[1].clone());
+        screens[0].new_scale = Some(1.0);
+        screen::mirror(&mut screens, 2, 0).unwrap();
+
+        let logical_monitors = logical_monitors(&screens, &state).unwrap();
+        assert_eq!(logical_monitors.len(), 1);
+
+        let (x, y, scale, transform, primary, monitors) = &logical_monitors[0];
+        assert_eq!((*x, *y, *scale, *transform, *primary), (0, 0, 1.0, 0, true));
+        let monitors: Vec<(&str, &str)> = monitors
+            .iter()
+            .map(|(connector, mode, _)| (connector.as_str(), mode.as_str()))
+            .collect();
+        assert_eq!(
+            monitors,
+            vec![
+                ("eDP-1", "1920x1080@60.000"),
+                ("HDMI-1", "1920x1080@60.000")
+            ]
+        );
+    }
+
+    #[test]
+    fn needs_mutter_on_the_bus() {
+        let Some(bus) = Bus::start() else {
+            return;
+        };
+
+        let error = bus.mutter().get_screens().unwrap_err().to_string();
+        assert!(error.contains(DESTINATION), "{}", error);
+
+        let Some(stub) = stub(false) else {
+            return;
+        };
+        assert_eq!(stub.backend.get_screens().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn verifies_then_applies_temporarily_until_kept() {
+        let Some(stub) = stub(false) else {
+            return;
+        };
+        let Stub {
+            backend, applied, ..
+        } = &stub;
+
+        let mut screens = backend.get_screens().unwrap();
+        assert_eq!(screens.len(), 3);
+
+        screens[1].new_position = Some((0, 1080));
+        screens[1].new_rotation = Some(Rotation::Normal);
+        backend.apply(&screens).unwrap();
+
+        {
+            let applied = applied.lock().unwrap();
+            let methods: Vec<u32> = applied.iter().map(|(method, _)| *method).collect();
+            assert_eq!(methods, vec![VERIFY, TEMPORARY]);
+
+            let (x, y, _, transform, primary, _) = &applied[1].1[1];
+            assert_eq!((*x, *y, *transform, *primary), (0, 1080, 0, false));
+        }
+
+        backend.keep().unwrap();
+        assert_eq!(applied.lock().unwrap().last().unwrap().0, PERSISTENT);
+
+        // Drawing the preview does not wait on the bus
+        let reads = stub.reads.load(Ordering::SeqCst);
+        backend.preview(&screens);
+        assert_eq!(stub.reads.load(Ordering::SeqCst), reads);
+
+        assert_eq!(
+            backend.preview(&screens),
+            "gdbus call --session --dest org.gnome.Mutter.DisplayConfig \
+             --object-path /org/gnome/Mutter/DisplayConfig \
+             --method org.gnome.Mutter.DisplayConfig.ApplyMonitorsConfig 7 1 \
+             '[(0, 0, 2.0, uint32 0, true, [(\"eDP-1\", \"3840x2160@60.000\", @a{sv} {})]), \
+             (0, 1080, 1.0, uint32 0, false, [(\"DP-2\", \"2560x1440@59.951\", @a{sv} {})])]' \
+             '{}'"
+        );
+    }
+
+    #[test]
+    fn does_not_apply_a_rejected_configuration() {
+        let Some(stub) = stub(true) else {
+            return;
+        };
+        let Stub {
+            backend, applied, ..
+        } = &stub;
+
+        let mut screens = backend.get_screens().unwrap();
+        screens[2].new_enabled = Some(true);
+
+        let error = backend.apply(&screens).unwrap_err().to_string();
+        assert!(
+            error.starts_with("Mutter rejected the configuration"),
+            "{}",
+            error
+        );
+        assert!(applied.lock().unwrap().is_empty());
+    }
+}
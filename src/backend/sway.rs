@@ -0,0 +1,359 @@
+use std::{
+    io::{Read, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    app::AppResult,
+    backend::{from_transform, quote, transform, DisplayBackend, TRANSFORMS},
+    edid,
+    screen::{Mode, Screen},
+};
+
+const MAGIC: &[u8; 6] = b"i3-ipc";
+const RUN_COMMAND: u32 = 0;
+const GET_OUTPUTS: u32 = 3;
+
+/// The sway compositor, spoken to through its IPC socket.
+#[derive(Debug)]
+pub struct Sway {
+    socket: PathBuf,
+}
+
+impl Sway {
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Self {
+            socket: socket.into(),
+        }
+    }
+
+    /// Uses the socket of the running sway, from `SWAYSOCK`.
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os("SWAYSOCK").map(Self::new)
+    }
+
+    /// Sends a message and returns the payload of the reply.
+    fn request(&self, kind: u32, payload: &str) -> AppResult<String> {
+        let mut stream = UnixStream::connect(&self.socket)?;
+
+        let mut message = MAGIC.to_vec();
+        message.extend((payload.len() as u32).to_ne_bytes());
+        message.extend(kind.to_ne_bytes());
+        message.extend(payload.as_bytes());
+        stream.write_all(&message)?;
+
+        let mut header = [0u8; 14];
+        stream.read_exact(&mut header)?;
+        if &header[..6] != MAGIC {
+            return Err("Invalid reply from sway".into());
+        }
+
+        let length = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]);
+        let mut reply = vec![0u8; length as usize];
+        stream.read_exact(&mut reply)?;
+
+        Ok(String::from_utf8(reply)?)
+    }
+}
+
+impl DisplayBackend for Sway {
+    fn get_screens(&self) -> AppResult<Vec<Screen>> {
+        let mut screens = screens(&self.request(GET_OUTPUTS, "")?)?;
+
+        for screen in screens.iter_mut() {
+            screen.edid = edid::read_sysfs(Path::new("/sys/class/drm"), &screen.name);
+        }
+
+        Ok(screens)
+    }
+
+    fn apply(&self, screens: &[Screen]) -> AppResult<()> {
+        let commands = commands(screens);
+        if commands.is_empty() {
+            return Ok(());
+        }
+
+        let results: Vec<CommandResult> =
+            serde_json::from_str(&self.request(RUN_COMMAND, &commands.join("; "))?)?;
+
+        let errors: Vec<String> = results
+            .into_iter()
+            .filter(|result| !result.success)
+            .map(|result| result.error.unwrap_or_else(|| "Unknown error".to_string()))
+            .collect();
+
+        if !errors.is_empty() {
+            return Err(errors.join(", ").into());
+        }
+
+        Ok(())
+    }
+
+    fn preview(&self, screens: &[Screen]) -> String {
+        format!("swaymsg {}", quote(&commands(screens).join("; ")))
+    }
+
+    fn has_primary(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Output {
+    name: String,
+    active: bool,
+    rect: Rect,
+    modes: Vec<OutputMode>,
+    current_mode: Option<OutputMode>,
+    transform: Option<String>,
+    scale: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rect {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct OutputMode {
+    width: u16,
+    height: u16,
+    /// In mHz.
+    refresh: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandResult {
+    success: bool,
+    error: Option<String>,
+}
+
+/// Converts the outputs of the `get_outputs` reply to screens.
+pub fn screens(json: &str) -> AppResult<Vec<Screen>> {
+    let outputs: Vec<Output> = serde_json::from_str(json)?;
+
+    Ok(outputs
+        .into_iter()
+        .map(|output| {
+            let current = output
+                .current_mode
+                .as_ref()
+                .filter(|_| output.active)
+                .map(|mode| (mode.width, mode.height, mode.refresh));
+
+            let modes: Vec<Mode> = output
+                .modes
+                .iter()
+                .map(|mode| Mode {
+                    name: format!("{}x{}", mode.width, mode.height),
+                    width: mode.width,
+                    height: mode.height,
+                    refresh: mode.refresh as f32 / 1000.0,
+                    is_current: current == Some((mode.width, mode.height, mode.refresh)),
+                    // sway does not tell which mode is the preferred one
+                    is_preferred: false,
+                })
+                .collect();
+
+            // Disabled outputs would be turned on with their first mode
+            let resolution = current
+                .map(|(width, height, _)| (width, height))
+                .or(modes.first().map(|mode| (mode.width, mode.height)))
+                .unwrap_or((0, 0));
+
+            let transform = output
+                .transform
+                .as_deref()
+                .and_then(|name| TRANSFORMS.iter().position(|t| *t == name))
+                .unwrap_or(0);
+            let (rotation, reflection) = from_transform(transform as u8);
+
+            Screen {
+                name: output.name,
+                enabled: output.active,
+                resolution,
+                position: (output.rect.x.max(0) as u16, output.rect.y.max(0) as u16),
+                modes,
+                rotation,
+                reflection,
+                // Disabled outputs have a scale of -1
+                scale: output.scale.filter(|scale| *scale > 0.0).unwrap_or(1.0),
+                ..Default::default()
+            }
+        })
+        .collect())
+}
+
+/// Builds the `output` commands applying the pending changes of the screens.
+pub fn commands(screens: &[Screen]) -> Vec<String> {
+    screens
+        .iter()
+        .filter(|screen| screen.has_pending_changes())
+        .map(|screen| {
+            let name = format!("\"{}\"", screen.name);
+
+            if !screen.is_enabled() {
+                return format!("output {} disable", name);
+            }
+
+            let mut command = format!("output {} enable", name);
+
+            if let Some(mode) = screen.mode() {
+                command.push_str(&format!(
+                    " mode {}x{}@{:.3}Hz",
+                    mode.width, mode.height, mode.refresh
+                ));
+            }
+
+            let (x, y) = screen.pending_position();
+            let transform = transform(screen.pending_rotation(), screen.pending_reflection());
+            command.push_str(&format!(
+                " pos {} {} transform {} scale {}",
+                x,
+                y,
+                TRANSFORMS[transform as usize],
+                screen.pending_scale()
+            ));
+
+            command
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::net::UnixListener, sync::mpsc, thread};
+
+    use super::*;
+    use crate::screen::{Reflection, Rotation};
+
+    const LAPTOP_DOCKED: &str = include_str!("../../tests/fixtures/sway/laptop-docked.json");
+
+    /// Serves `replies` on a fake sway socket, one connection each, and
+    /// sends back the messages received as `(type, payload)`.
+    fn fake_sway(
+        socket: &Path,
+        replies: Vec<&'static str>,
+    ) -> (Sway, mpsc::Receiver<(u32, String)>) {
+        let listener = UnixListener::bind(socket).unwrap();
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for reply in replies {
+                let (mut stream, _) = listener.accept().unwrap();
+
+                let mut header = [0u8; 14];
+                stream.read_exact(&mut header).unwrap();
+                let length = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]);
+                let kind = u32::from_ne_bytes([header[10], header[11], header[12], header[13]]);
+                let mut payload = vec![0u8; length as usize];
+                stream.read_exact(&mut payload).unwrap();
+                sender
+                    .send((kind, String::from_utf8(payload).unwrap()))
+                    .unwrap();
+
+                let mut message = MAGIC.to_vec();
+                message.extend((reply.len() as u32).to_ne_bytes());
+                message.extend(kind.to_ne_bytes());
+                message.extend(reply.as_bytes());
+                stream.write_all(&message).unwrap();
+            }
+        });
+
+        (Sway::new(socket), receiver)
+    }
+
+    #[test]
+    fn converts_outputs_to_screens() {
+        let screens = screens(LAPTOP_DOCKED).unwrap();
+
+        assert_eq!(screens.len(), 3);
+
+        let edp = &screens[0];
+        assert!(edp.enabled);
+        assert_eq!(edp.resolution, (3840, 2160));
+        assert_eq!(edp.size(), (1920, 1080));
+        assert_eq!(edp.mode().unwrap().refresh, 60.0);
+
+        let dp = &screens[1];
+        assert_eq!(dp.position, (1920, 0));
+        assert_eq!(dp.rotation, Rotation::Left);
+        assert_eq!(dp.reflection, Reflection::X);
+        assert_eq!(dp.size(), (1440, 2560));
+        assert_eq!(dp.mode().unwrap().refresh, 59.951);
+
+        let hdmi = &screens[2];
+        assert!(!hdmi.enabled);
+        assert_eq!(hdmi.scale, 1.0);
+        assert_eq!(hdmi.resolution, (1920, 1080));
+        assert!(hdmi.mode().is_none());
+    }
+
+    #[test]
+    fn builds_the_commands_of_the_pending_changes() {
+        let mut screens = screens(LAPTOP_DOCKED).unwrap();
+        screens[1].new_enabled = Some(false);
+        screens[2].new_enabled = Some(true);
+        screens[2].new_position = Some((1920, 0));
+        screens[2].new_rotation = Some(Rotation::Inverted);
+
+        assert_eq!(
+            commands(&screens),
+            vec![
+                "output \"DP-2\" disable",
+                "output \"HDMI-A-1\" enable pos 1920 0 transform 180 scale 1",
+            ]
+        );
+        assert_eq!(
+            Sway::new("/dev/null").preview(&screens),
+            "swaymsg 'output \"DP-2\" disable; output \"HDMI-A-1\" enable pos 1920 0 transform 180 scale 1'"
+        );
+    }
+
+    #[test]
+    fn applies_the_commands_over_ipc() {
+        let dir = tempfile::tempdir().unwrap();
+        let (sway, messages) = fake_sway(
+            &dir.path().join("sway.sock"),
+            vec![LAPTOP_DOCKED, r#"[{"success": true}]"#],
+        );
+
+        let mut screens = sway.get_screens().unwrap();
+        assert_eq!(messages.recv().unwrap(), (GET_OUTPUTS, String::new()));
+
+        screens[0].new_scale = Some(1.5);
+        sway.apply(&screens).unwrap();
+        assert_eq!(
+            messages.recv().unwrap(),
+            (
+                RUN_COMMAND,
+                "output \"eDP-1\" enable mode 3840x2160@60.000Hz pos 0 0 transform normal scale 1.5"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn reports_the_failed_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let (sway, _messages) = fake_sway(
+            &dir.path().join("sway.sock"),
+            vec![
+                LAPTOP_DOCKED,
+                r#"[{"success": false, "parse_error": false, "error": "Invalid output mode"}]"#,
+            ],
+        );
+
+        let mut screens = sway.get_screens().unwrap();
+        screens[1].new_mode = Some(screens[1].modes[1].clone());
+
+        assert_eq!(
+            sway.apply(&screens).unwrap_err().to_string(),
+            "Invalid output mode"
+        );
+    }
+}
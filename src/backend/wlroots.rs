@@ -0,0 +1,309 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{
+    app::AppResult,
+    backend::{from_transform, quote, resolution, transform, DisplayBackend, TRANSFORMS},
+    edid,
+    screen::{Mode, Screen},
+};
+
+command_backend!(
+    /// Wayland compositors implementing `wlr-output-management` (sway, river,
+    /// labwc...), driven through `wlr-randr`.
+    Wlroots,
+    "wlr-randr"
+);
+
+impl DisplayBackend for Wlroots {
+    fn get_screens(&self) -> AppResult<Vec<Screen>> {
+        let mut screens = screens(&self.run(&["--json".to_string()])?)?;
+
+        for screen in screens.iter_mut() {
+            screen.edid = edid::read_sysfs(Path::new("/sys/class/drm"), &screen.name);
+        }
+
+        Ok(screens)
+    }
+
+    /// Tests the configuration with `--dryrun` before applying it, so the
+    /// compositor never ends up with half of it.
+    fn apply(&self, screens: &[Screen]) -> AppResult<()> {
+        let args = arguments(screens);
+        if args.is_empty() {
+            return Ok(());
+        }
+
+        let mut dry_run = vec!["--dryrun".to_string()];
+        dry_run.extend(args.iter().cloned());
+        self.run(&dry_run)
+            .map_err(|e| format!("The compositor rejected the configuration: {}", e))?;
+
+        self.run(&args)?;
+        Ok(())
+    }
+
+    fn preview(&self, screens: &[Screen]) -> String {
+        let mut command = vec!["wlr-randr".to_string()];
+        command.extend(arguments(screens).iter().map(|arg| quote(arg)));
+        command.join(" ")
+    }
+
+    fn has_primary(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Head {
+    name: String,
+    enabled: bool,
+    modes: Vec<HeadMode>,
+    position: Option<Position>,
+    transform: Option<String>,
+    scale: Option<f32>,
+    physical_size: Option<PhysicalSize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HeadMode {
+    width: u16,
+    height: u16,
+    refresh: f32,
+    preferred: bool,
+    current: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Position {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhysicalSize {
+    width: u32,
+    height: u32,
+}
+
+/// Converts the heads printed by `wlr-randr --json` to screens.
+pub fn screens(json: &str) -> AppResult<Vec<Screen>> {
+    let heads: Vec<Head> = serde_json::from_str(json)?;
+
+    Ok(heads
+        .into_iter()
+        .map(|head| {
+            let modes: Vec<Mode> = head
+                .modes
+                .iter()
+                .map(|mode| Mode {
+                    name: format!("{}x{}", mode.width, mode.height),
+                    width: mode.width,
+                    height: mode.height,
+                    refresh: mode.refresh,
+                    // Disabled heads keep reporting their last mode as current
+                    is_current: mode.current && head.enabled,
+                    is_preferred: mode.preferred,
+                })
+                .collect();
+
+            let transform = head
+                .transform
+                .as_deref()
+                .and_then(|name| TRANSFORMS.iter().position(|t| *t == name))
+                .unwrap_or(0);
+            let (rotation, reflection) = from_transform(transform as u8);
+
+            let position = head
+                .position
+                .map_or((0, 0), |p| (p.x.max(0) as u16, p.y.max(0) as u16));
+
+            Screen {
+                name: head.name,
+                enabled: head.enabled,
+                resolution: resolution(&modes),
+                position,
+                modes,
+                physical_size: head
+                    .physical_size
+                    .filter(|size| size.width > 0 && size.height > 0)
+                    .map(|size| (size.width, size.height)),
+                rotation,
+                reflection,
+                scale: head.scale.filter(|scale| *scale > 0.0).unwrap_or(1.0),
+                ..Default::default()
+            }
+        })
+        .collect())
+}
+
+/// Builds the wlr-randr arguments applying the pending changes of the screens.
+pub fn arguments(screens: &[Screen]) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+
+    for screen in screens.iter().filter(|screen| screen.has_pending_changes()) {
+        args.extend(["--output".to_string(), screen.name.clone()]);
+
+        if !screen.is_enabled() {
+            args.push("--off".to_string());
+            continue;
+        }
+
+        args.push("--on".to_string());
+
+        if let Some(mode) = screen.mode() {
+            args.extend([
+                "--mode".to_string(),
+                format!("{}x{}@{:.3}Hz", mode.width, mode.height, mode.refresh),
+            ]);
+        }
+
+        let (x, y) = screen.pending_position();
+        let transform = transform(screen.pending_rotation(), screen.pending_reflection());
+        args.extend([
+            "--pos".to_string(),
+            format!("{},{}", x, y),
+            "--transform".to_string(),
+            TRANSFORMS[transform as usize].to_string(),
+            "--scale".to_string(),
+            screen.pending_scale().to_string(),
+        ]);
+    }
+
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::fake::{self, calls},
+        screen::{Reflection, Rotation},
+    };
+
+    const LAPTOP_DOCKED: &str = include_str!("../../tests/fixtures/wlroots/laptop-docked.json");
+
+    /// A fake `wlr-randr` failing the dry runs when `reject` is set.
+    fn fake_wlr_randr(dir: &Path, reject: bool) -> Wlroots {
+        let otherwise = if reject {
+            "[ \"$1\" != --dryrun ] || { echo 'invalid mode' >&2; exit 1; }"
+        } else {
+            ":"
+        };
+        Wlroots::new(fake::program(
+            dir,
+            "wlr-randr",
+            "--json",
+            "wlroots/laptop-docked.json",
+            otherwise,
+        ))
+    }
+
+    #[test]
+    fn converts_heads_to_screens() {
+        let screens = screens(LAPTOP_DOCKED).unwrap();
+
+        assert_eq!(screens.len(), 3);
+
+        let edp = &screens[0];
+        assert_eq!(edp.resolution, (3840, 2160));
+        assert_eq!(edp.scale, 2.0);
+        assert_eq!(edp.size(), (1920, 1080));
+        assert_eq!(edp.physical_size, Some((310, 170)));
+        assert!(!edp.is_primary);
+
+        let dp = &screens[1];
+        assert_eq!(dp.position, (1920, 0));
+        assert_eq!(dp.rotation, Rotation::Left);
+        assert_eq!(dp.reflection, Reflection::Normal);
+        assert_eq!(dp.size(), (1440, 2560));
+        assert_eq!(dp.mode().unwrap().refresh, 59.951);
+
+        let hdmi = &screens[2];
+        assert!(!hdmi.enabled);
+        assert_eq!(hdmi.resolution, (1920, 1080));
+        assert_eq!(hdmi.physical_size, None);
+        assert!(hdmi.mode().is_none());
+    }
+
+    #[test]
+    fn builds_the_arguments_of_the_pending_changes() {
+        let mut screens = screens(LAPTOP_DOCKED).unwrap();
+        screens[0].new_scale = Some(1.5);
+        screens[1].new_enabled = Some(false);
+        screens[2].new_enabled = Some(true);
+        screens[2].new_position = Some((2560, 0));
+        screens[2].new_reflection = Some(Reflection::Y);
+
+        assert_eq!(
+            arguments(&screens),
+            vec![
+                "--output",
+                "eDP-1",
+                "--on",
+                "--mode",
+                "3840x2160@60.000Hz",
+                "--pos",
+                "0,0",
+                "--transform",
+                "normal",
+                "--scale",
+                "1.5",
+                "--output",
+                "DP-2",
+                "--off",
+                "--output",
+                "HDMI-A-1",
+                "--on",
+                "--pos",
+                "2560,0",
+                "--transform",
+                "flipped-180",
+                "--scale",
+                "1",
+            ]
+        );
+    }
+
+    #[test]
+    fn tests_the_configuration_before_applying_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = fake_wlr_randr(dir.path(), false);
+
+        let mut screens = backend.get_screens().unwrap();
+        assert_eq!(screens.len(), 3);
+
+        screens[1].new_position = Some((3840, 0));
+        backend.apply(&screens).unwrap();
+
+        let args =
+            "--output DP-2 --on --mode 2560x1440@59.951Hz --pos 3840,0 --transform 90 --scale 1";
+        assert_eq!(
+            calls(dir.path()),
+            vec![
+                "--json".to_string(),
+                format!("--dryrun {}", args),
+                args.to_string()
+            ]
+        );
+        assert_eq!(backend.preview(&screens), format!("wlr-randr {}", args));
+    }
+
+    #[test]
+    fn does_not_apply_a_rejected_configuration() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = fake_wlr_randr(dir.path(), true);
+
+        let mut screens = backend.get_screens().unwrap();
+        screens[0].new_scale = Some(1.0);
+
+        let error = backend.apply(&screens).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The compositor rejected the configuration: invalid mode"
+        );
+        assert_eq!(calls(dir.path()).len(), 2);
+        assert!(calls(dir.path())[1].starts_with("--dryrun"));
+    }
+}
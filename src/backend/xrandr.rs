@@ -0,0 +1,526 @@
+use std::path::Path;
+
+use crate::{
+    app::AppResult,
+    backend::{quote, DisplayBackend},
+    edid::{self, Edid},
+    screen::{Location, Screen},
+};
+
+pub mod parser;
+
+use parser::XrandrState;
+
+command_backend!(
+    /// X11, driven through `xrandr`.
+    Xrandr,
+    "xrandr"
+);
+
+impl DisplayBackend for Xrandr {
+    fn get_screens(&self) -> AppResult<Vec<Screen>> {
+        let output = self.run(&["--verbose".to_string()])?;
+
+        let mut screens = screens(parser::parse(&output)?);
+
+        // Some drivers do not expose the EDID property
+        for screen in screens.iter_mut().filter(|screen| screen.edid.is_none()) {
+            screen.edid = edid::read_sysfs(Path::new("/sys/class/drm"), &screen.name);
+        }
+
+        Ok(screens)
+    }
+
+    fn apply(&self, screens: &[Screen]) -> AppResult<()> {
+        let args = arguments(screens);
+        if args.is_empty() {
+            return Ok(());
+        }
+
+        self.run(&args)?;
+        Ok(())
+    }
+
+    fn preview(&self, screens: &[Screen]) -> String {
+        let mut command = vec!["xrandr".to_string()];
+        command.extend(arguments(screens).iter().map(|arg| quote(arg)));
+        command.join(" ")
+    }
+}
+
+/// Converts the connected outputs to screens.
+pub fn screens(state: XrandrState) -> Vec<Screen> {
+    state
+        .outputs
+        .into_iter()
+        .filter(|output| output.is_connected())
+        .map(|output| {
+            let (resolution, position, scale) = match output.geometry {
+                Some(geometry) => {
+                    let (width, height) = if output.rotation.is_sideways() {
+                        (geometry.height as u16, geometry.width as u16)
+                    } else {
+                        (geometry.width as u16, geometry.height as u16)
+                    };
+
+                    // The geometry is the mode size divided by the scale
+                    let (resolution, scale) = match output.current_mode() {
+                        Some(mode) if mode.width > 0 && width > 0 => {
+                            ((mode.width, mode.height), mode.width as f32 / width as f32)
+                        }
+                        _ => ((width, height), 1.0),
+                    };
+
+                    (resolution, (geometry.x as u16, geometry.y as u16), scale)
+                }
+                // Disabled outputs would be turned on with their preferred mode
+                None => {
+                    let resolution = output
+                        .modes
+                        .iter()
+                        .find(|mode| mode.is_preferred)
+                        .or(output.modes.first())
+                        .map_or((0, 0), |mode| (mode.width, mode.height));
+                    (resolution, (0, 0), 1.0)
+                }
+            };
+
+            let edid = output
+                .property("EDID")
+                .and_then(|property| Edid::from_hex(&property.details.join("")).ok());
+
+            Screen {
+                name: output.name,
+                is_primary: output.is_primary,
+                enabled: output.geometry.is_some(),
+                resolution,
+                position,
+                modes: output.modes,
+                physical_size: output.physical_size,
+                rotation: output.rotation,
+                reflection: output.reflection,
+                scale,
+                edid,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Builds the xrandr arguments applying the pending changes of the screens.
+pub fn arguments(screens: &[Screen]) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+
+    for screen in screens.iter().filter(|screen| screen.has_pending_changes()) {
+        if !screen.is_enabled() {
+            args.extend([
+                "--output".to_string(),
+                screen.name.clone(),
+                "--off".to_string(),
+            ]);
+            continue;
+        }
+
+        args.extend(["--output".to_string(), screen.name.clone()]);
+
+        match screen.mode() {
+            Some(mode) => args.extend([
+                "--mode".to_string(),
+                mode.name.clone(),
+                "--rate".to_string(),
+                format!("{:.2}", mode.refresh),
+            ]),
+            None => args.push("--auto".to_string()),
+        }
+
+        args.extend([
+            "--rotate".to_string(),
+            screen.pending_rotation().to_string(),
+            "--reflect".to_string(),
+            screen.pending_reflection().to_string(),
+        ]);
+
+        let mirrored = match (&screen.location, &screen.relative_to) {
+            (Some(Location::SAME), Some(name)) => screens.iter().find(|s| &s.name == name),
+            _ => None,
+        };
+
+        if let Some(mirrored) = mirrored.filter(|_| screen.pending_scale() != 1.0) {
+            // Fit the whole mirrored screen, even with another aspect ratio
+            let (width, height) = mirrored.size();
+            args.extend(["--scale-from".to_string(), format!("{}x{}", width, height)]);
+        } else if screen.pending_scale() != 1.0 || screen.scale != 1.0 {
+            // xrandr scales the other way around: 0.5x0.5 doubles the size of the text
+            let scale = 1.0 / screen.pending_scale();
+            args.extend(["--scale".to_string(), format!("{}x{}", scale, scale)]);
+        }
+
+        match mirrored {
+            Some(mirrored) => args.extend(["--same-as".to_string(), mirrored.name.clone()]),
+            // Exact positions rather than --right-of and co, which can not express offsets
+            None => {
+                let (x, y) = screen.pending_position();
+                args.extend(["--pos".to_string(), format!("{}x{}", x, y)]);
+            }
+        }
+
+        if screen.pending_primary() && !screen.is_primary {
+            args.push("--primary".to_string());
+        }
+    }
+
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::fake::{self, calls},
+        screen::{Mode, Reflection, Rotation},
+    };
+
+    fn screen(name: &str, position: (u16, u16)) -> Screen {
+        Screen {
+            name: name.to_string(),
+            enabled: true,
+            resolution: (1920, 1080),
+            position,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn converts_connected_outputs_to_screens() {
+        let state = parser::parse(include_str!(
+            "../../tests/fixtures/xrandr/desktop-query.txt"
+        ))
+        .unwrap();
+        let screens = screens(state);
+
+        assert_eq!(screens.len(), 2);
+
+        let hdmi = &screens[0];
+        assert_eq!(hdmi.name, "HDMI-0");
+        assert!(hdmi.enabled && !hdmi.is_primary);
+        assert_eq!(hdmi.rotation, Rotation::Left);
+        assert_eq!(hdmi.resolution, (1920, 1080));
+        assert_eq!(hdmi.size(), (1080, 1920));
+
+        let dp = &screens[1];
+        assert!(dp.is_primary);
+        assert_eq!(dp.position, (1080, 0));
+        assert_eq!(dp.mode().unwrap().refresh, 144.0);
+    }
+
+    #[test]
+    fn decodes_the_edid_of_the_outputs() {
+        let state = parser::parse(include_str!(
+            "../../tests/fixtures/xrandr/laptop-docked-verbose.txt"
+        ))
+        .unwrap();
+        let screens = screens(state);
+
+        let fingerprints: Vec<Option<String>> =
+            screens.iter().map(|screen| screen.fingerprint()).collect();
+        assert_eq!(
+            fingerprints,
+            vec![
+                Some("AUO-573d-00000000".to_string()),
+                Some("DEL-a0c4-5KC0F03".to_string()),
+                Some("BNQ-7f2a-00005445".to_string()),
+            ]
+        );
+
+        let dell = screens[1].edid.as_ref().unwrap();
+        assert_eq!(dell.name.as_deref(), Some("DELL U2719D"));
+        assert_eq!(dell.physical_size, (600, 340));
+    }
+
+    #[test]
+    fn serializes_screens_to_a_stable_json_schema() {
+        let state = parser::parse(include_str!(
+            "../../tests/fixtures/xrandr/laptop-docked-verbose.txt"
+        ))
+        .unwrap();
+        let mut screens = screens(state);
+        // Pending changes are not part of the schema
+        screens[1].new_position = Some((0, 0));
+
+        assert_eq!(
+            crate::screen::to_json(&screens).unwrap() + "\n",
+            include_str!("../../tests/fixtures/xrandr/laptop-docked.json")
+        );
+    }
+
+    #[test]
+    fn disabled_outputs_use_their_preferred_mode() {
+        let state =
+            parser::parse(include_str!("../../tests/fixtures/xrandr/laptop-query.txt")).unwrap();
+        let screens = screens(state);
+
+        let hdmi = screens.iter().find(|s| s.name == "HDMI2").unwrap();
+        assert!(!hdmi.enabled);
+        assert_eq!(hdmi.resolution, (1366, 768));
+        assert!(hdmi.mode().is_none());
+    }
+
+    #[test]
+    fn previews_the_command_line() {
+        let mut hdmi = screen("HDMI-1", (1920, 0));
+        hdmi.new_position = Some((0, 0));
+        hdmi.new_mode = Some(Mode {
+            name: "1920x1080 (0x4a)".to_string(),
+            width: 1920,
+            height: 1080,
+            refresh: 60.0,
+            is_current: false,
+            is_preferred: false,
+        });
+
+        let backend = Xrandr::default();
+        assert_eq!(backend.preview(&[screen("eDP-1", (0, 0))]), "xrandr");
+        assert_eq!(
+            backend.preview(&[hdmi]),
+            "xrandr --output HDMI-1 --mode '1920x1080 (0x4a)' --rate 60.00 \
+             --rotate normal --reflect normal --pos 0x0"
+        );
+    }
+
+    #[test]
+    fn applies_the_pending_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = Xrandr::new(fake::program(
+            dir.path(),
+            "xrandr",
+            "--verbose",
+            "xrandr/laptop-docked-verbose.txt",
+            ":",
+        ));
+
+        let mut screens = backend.get_screens().unwrap();
+        assert_eq!(screens.len(), 3);
+
+        // Nothing is run without changes
+        backend.apply(&screens).unwrap();
+        screens[1].new_position = Some((0, 0));
+        backend.apply(&screens).unwrap();
+
+        assert_eq!(
+            calls(dir.path()),
+            vec![
+                "--verbose",
+                "--output DP-1 --mode 2560x1440 --rate 59.95 --rotate normal --reflect normal --pos 0x0"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_a_failing_xrandr() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = Xrandr::new(fake::program(
+            dir.path(),
+            "xrandr",
+            "--query",
+            "xrandr/laptop-query.txt",
+            "echo \"Can't open display\" >&2; exit 1",
+        ));
+
+        assert_eq!(
+            backend.get_screens().unwrap_err().to_string(),
+            "Can't open display"
+        );
+    }
+
+    #[test]
+    fn skips_screens_without_changes() {
+        let screens = vec![screen("eDP-1", (0, 0)), screen("HDMI-1", (1920, 0))];
+
+        assert!(arguments(&screens).is_empty());
+    }
+
+    #[test]
+    fn places_screens_at_exact_positions() {
+        let mut hdmi = screen("HDMI-1", (1920, 0));
+        hdmi.new_position = Some((0, 0));
+        hdmi.location = Some(Location::LEFT);
+        hdmi.relative_to = Some("eDP-1".to_string());
+
+        assert_eq!(
+            arguments(&[screen("eDP-1", (0, 0)), hdmi]),
+            vec![
+                "--output",
+                "HDMI-1",
+                "--auto",
+                "--rotate",
+                "normal",
+                "--reflect",
+                "normal",
+                "--pos",
+                "0x0"
+            ]
+        );
+    }
+
+    #[test]
+    fn mirrors_with_same_as_and_scales_from_the_source() {
+        let mut edp = screen("eDP-1", (0, 0));
+        edp.resolution = (2560, 1440);
+
+        let mut hdmi = screen("HDMI-1", (2560, 0));
+        hdmi.new_position = Some((0, 0));
+        hdmi.new_scale = Some(0.75);
+        hdmi.location = Some(Location::SAME);
+        hdmi.relative_to = Some("eDP-1".to_string());
+
+        assert_eq!(
+            arguments(&[edp, hdmi]),
+            vec![
+                "--output",
+                "HDMI-1",
+                "--auto",
+                "--rotate",
+                "normal",
+                "--reflect",
+                "normal",
+                "--scale-from",
+                "2560x1440",
+                "--same-as",
+                "eDP-1"
+            ]
+        );
+    }
+
+    #[test]
+    fn applies_the_pending_mode_instead_of_auto() {
+        let mut edp = screen("eDP-1", (0, 0));
+        edp.modes = vec![Mode {
+            name: "1920x1080".to_string(),
+            width: 1920,
+            height: 1080,
+            refresh: 60.0,
+            is_current: true,
+            is_preferred: true,
+        }];
+
+        let mut dp = screen("DP-1", (1920, 0));
+        dp.new_mode = Some(Mode {
+            name: "2560x1440".to_string(),
+            width: 2560,
+            height: 1440,
+            refresh: 143.97,
+            is_current: false,
+            is_preferred: false,
+        });
+        dp.location = Some(Location::RIGHT);
+        dp.relative_to = Some("eDP-1".to_string());
+
+        assert_eq!(
+            arguments(&[edp, dp]),
+            vec![
+                "--output",
+                "DP-1",
+                "--mode",
+                "2560x1440",
+                "--rate",
+                "143.97",
+                "--rotate",
+                "normal",
+                "--reflect",
+                "normal",
+                "--pos",
+                "1920x0"
+            ]
+        );
+    }
+
+    #[test]
+    fn turns_screens_on_and_off() {
+        let mut hdmi = screen("HDMI-1", (1920, 0));
+        hdmi.new_enabled = Some(false);
+
+        let mut dp = screen("DP-1", (0, 0));
+        dp.enabled = false;
+        dp.new_enabled = Some(true);
+
+        assert_eq!(
+            arguments(&[screen("eDP-1", (0, 0)), hdmi, dp]),
+            vec![
+                "--output",
+                "HDMI-1",
+                "--off",
+                "--output",
+                "DP-1",
+                "--auto",
+                "--rotate",
+                "normal",
+                "--reflect",
+                "normal",
+                "--pos",
+                "0x0"
+            ]
+        );
+    }
+
+    #[test]
+    fn rotates_and_reflects_screens() {
+        let mut hdmi = screen("HDMI-1", (0, 0));
+        hdmi.new_rotation = Some(Rotation::Left);
+        hdmi.new_reflection = Some(Reflection::XY);
+
+        assert_eq!(
+            arguments(&[hdmi]),
+            vec![
+                "--output",
+                "HDMI-1",
+                "--auto",
+                "--rotate",
+                "left",
+                "--reflect",
+                "xy",
+                "--pos",
+                "0x0"
+            ]
+        );
+    }
+
+    #[test]
+    fn applies_absolute_positions_scale_and_primary() {
+        let mut edp = screen("eDP-1", (0, 0));
+        edp.is_primary = true;
+        edp.new_primary = Some(false);
+        edp.new_position = Some((1920, 0));
+
+        let mut hdmi = screen("HDMI-1", (1920, 0));
+        hdmi.new_primary = Some(true);
+        hdmi.new_position = Some((0, 0));
+        hdmi.new_scale = Some(2.0);
+
+        assert_eq!(
+            arguments(&[edp, hdmi]),
+            vec![
+                "--output",
+                "eDP-1",
+                "--auto",
+                "--rotate",
+                "normal",
+                "--reflect",
+                "normal",
+                "--pos",
+                "1920x0",
+                "--output",
+                "HDMI-1",
+                "--auto",
+                "--rotate",
+                "normal",
+                "--reflect",
+                "normal",
+                "--scale",
+                "0.5x0.5",
+                "--pos",
+                "0x0",
+                "--primary"
+            ]
+        );
+    }
+}
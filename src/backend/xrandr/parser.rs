@@ -0,0 +1,447 @@
+use regex::Regex;
+
+use crate::{
+    app::AppResult,
+    screen::{Mode, Reflection, Rotation},
+};
+
+/// Everything reported by `xrandr --query` or `xrandr --verbose`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct XrandrState {
+    pub screen: Option<ScreenSize>,
+    pub outputs: Vec<Output>,
+}
+
+/// The `Screen 0: minimum 320 x 200, current 4480 x 1440, maximum 16384 x 16384` header.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ScreenSize {
+    pub number: u32,
+    pub minimum: (u32, u32),
+    pub current: (u32, u32),
+    pub maximum: (u32, u32),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Connection {
+    Connected,
+    Disconnected,
+    #[default]
+    Unknown,
+}
+
+/// Size and position of an active output in the screen.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Geometry {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Output {
+    pub name: String,
+    pub connection: Connection,
+    pub is_primary: bool,
+    /// `None` when the output is disabled.
+    pub geometry: Option<Geometry>,
+    /// Only reported by `--verbose`.
+    pub mode_id: Option<String>,
+    pub rotation: Rotation,
+    pub reflection: Reflection,
+    pub physical_size: Option<(u32, u32)>,
+    /// The CRTC driving the output, only reported by `--verbose`.
+    pub crtc: Option<u32>,
+    /// The CRTCs able to drive the output, only reported by `--verbose`.
+    pub crtcs: Vec<u32>,
+    pub properties: Vec<Property>,
+    pub modes: Vec<Mode>,
+}
+
+/// An output property such as `EDID` or `Broadcast RGB`.
+///
+/// `details` holds the indented lines following the property, e.g. the EDID
+/// hex dump or the `supported: ...` values.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Property {
+    pub name: String,
+    pub value: String,
+    pub details: Vec<String>,
+}
+
+impl Output {
+    pub fn is_connected(&self) -> bool {
+        self.connection == Connection::Connected
+    }
+
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    pub fn current_mode(&self) -> Option<&Mode> {
+        self.modes.iter().find(|mode| mode.is_current)
+    }
+}
+
+pub fn parse(input: &str) -> AppResult<XrandrState> {
+    let re_screen = Regex::new(
+        r"^Screen (\d+): minimum (\d+) x (\d+), current (\d+) x (\d+), maximum (\d+) x (\d+)",
+    )?;
+
+    let re_output = Regex::new(
+        r"^(\S+) (connected|disconnected|unknown connection)( primary)?(?: (\d+)x(\d+)\+(\d+)\+(\d+))?(?: \((0x[0-9a-f]+)\))?(?: (normal|left|inverted|right))?(?: (X axis|Y axis|X and Y axis))?(?: \([^)]*\))?(?: (\d+)mm x (\d+)mm)?",
+    )?;
+
+    let re_verbose_mode = Regex::new(r"^  (\S+) \((0x[0-9a-f]+)\)\s+[\d.]+MHz(.*)$")?;
+    let re_verbose_width = Regex::new(r"^\s+h: width\s+(\d+)")?;
+    let re_verbose_height = Regex::new(r"^\s+v: height\s+(\d+).*clock\s+([\d.]+)Hz")?;
+
+    let re_mode = Regex::new(r"^\s+(\S+)((?:\s+[\d.]+\*?\s?\+?)+)\s*$")?;
+    let re_rate = Regex::new(r"([\d.]+)(\*)?( ?\+)?")?;
+    let re_size = Regex::new(r"^(\d+)x(\d+)")?;
+
+    let mut state = XrandrState::default();
+
+    for line in input.lines() {
+        if let Some(cap) = re_screen.captures(line) {
+            state.screen = Some(ScreenSize {
+                number: cap[1].parse()?,
+                minimum: (cap[2].parse()?, cap[3].parse()?),
+                current: (cap[4].parse()?, cap[5].parse()?),
+                maximum: (cap[6].parse()?, cap[7].parse()?),
+            });
+            continue;
+        }
+
+        if let Some(cap) = re_output.captures(line) {
+            let connection = match &cap[2] {
+                "connected" => Connection::Connected,
+                "disconnected" => Connection::Disconnected,
+                _ => Connection::Unknown,
+            };
+
+            let geometry = match (cap.get(4), cap.get(5), cap.get(6), cap.get(7)) {
+                (Some(width), Some(height), Some(x), Some(y)) => Some(Geometry {
+                    width: width.as_str().parse()?,
+                    height: height.as_str().parse()?,
+                    x: x.as_str().parse()?,
+                    y: y.as_str().parse()?,
+                }),
+                _ => None,
+            };
+
+            let rotation = match cap.get(9).map(|m| m.as_str()) {
+                Some("left") => Rotation::Left,
+                Some("inverted") => Rotation::Inverted,
+                Some("right") => Rotation::Right,
+                _ => Rotation::Normal,
+            };
+
+            let reflection = match cap.get(10).map(|m| m.as_str()) {
+                Some("X axis") => Reflection::X,
+                Some("Y axis") => Reflection::Y,
+                Some("X and Y axis") => Reflection::XY,
+                _ => Reflection::Normal,
+            };
+
+            let physical_size = match (cap.get(11), cap.get(12)) {
+                (Some(width), Some(height)) => {
+                    Some((width.as_str().parse()?, height.as_str().parse()?))
+                }
+                _ => None,
+            };
+
+            state.outputs.push(Output {
+                name: cap[1].to_string(),
+                connection,
+                is_primary: cap.get(3).is_some(),
+                geometry,
+                mode_id: cap.get(8).map(|m| m.as_str().to_string()),
+                rotation,
+                reflection,
+                physical_size,
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let Some(output) = state.outputs.last_mut() else {
+            continue;
+        };
+
+        // Verbose mode, followed by its "h:" and "v:" lines
+        if let Some(cap) = re_verbose_mode.captures(line) {
+            let flags = &cap[3];
+            let (width, height) = match re_size.captures(&cap[1]) {
+                Some(size) => (size[1].parse()?, size[2].parse()?),
+                None => (0, 0),
+            };
+
+            output.modes.push(Mode {
+                name: cap[1].to_string(),
+                width,
+                height,
+                refresh: 0.0,
+                is_current: flags.contains("*current"),
+                is_preferred: flags.contains("+preferred"),
+            });
+            continue;
+        }
+
+        if let Some(cap) = re_verbose_width.captures(line) {
+            if let Some(mode) = output.modes.last_mut() {
+                mode.width = cap[1].parse()?;
+            }
+            continue;
+        }
+
+        if let Some(cap) = re_verbose_height.captures(line) {
+            if let Some(mode) = output.modes.last_mut() {
+                mode.height = cap[1].parse()?;
+                mode.refresh = cap[2].parse()?;
+            }
+            continue;
+        }
+
+        // Property details (EDID dump, supported values, transform matrix...)
+        if line.starts_with("\t\t") || line.starts_with("\t ") {
+            if let Some(property) = output.properties.last_mut() {
+                property.details.push(line.trim().to_string());
+            }
+            continue;
+        }
+
+        if let Some(property) = line.strip_prefix('\t') {
+            if let Some((name, value)) = property.split_once(':') {
+                let value = value.trim().to_string();
+
+                match name {
+                    "CRTC" => output.crtc = value.parse().ok(),
+                    "CRTCs" => {
+                        output.crtcs = value
+                            .split_whitespace()
+                            .filter_map(|crtc| crtc.parse().ok())
+                            .collect()
+                    }
+                    _ => {}
+                }
+
+                output.properties.push(Property {
+                    name: name.to_string(),
+                    value,
+                    details: Vec::new(),
+                });
+            }
+            continue;
+        }
+
+        // Mode from the default output, with all its refresh rates
+        if let Some(cap) = re_mode.captures(line) {
+            let name = &cap[1];
+            let (width, height) = match re_size.captures(name) {
+                Some(size) => (size[1].parse()?, size[2].parse()?),
+                None => (0, 0),
+            };
+
+            for rate in re_rate.captures_iter(&cap[2]) {
+                output.modes.push(Mode {
+                    name: name.to_string(),
+                    width,
+                    height,
+                    refresh: rate[1].parse()?,
+                    is_current: rate.get(2).is_some(),
+                    is_preferred: rate.get(3).is_some(),
+                });
+            }
+        }
+    }
+
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAPTOP_DOCKED_VERBOSE: &str =
+        include_str!("../../../tests/fixtures/xrandr/laptop-docked-verbose.txt");
+    const DESKTOP_QUERY: &str = include_str!("../../../tests/fixtures/xrandr/desktop-query.txt");
+    const LAPTOP_QUERY: &str = include_str!("../../../tests/fixtures/xrandr/laptop-query.txt");
+
+    fn output<'a>(state: &'a XrandrState, name: &str) -> &'a Output {
+        state.outputs.iter().find(|o| o.name == name).unwrap()
+    }
+
+    #[test]
+    fn parses_the_screen_header() {
+        let state = parse(LAPTOP_DOCKED_VERBOSE).unwrap();
+
+        assert_eq!(
+            state.screen,
+            Some(ScreenSize {
+                number: 0,
+                minimum: (320, 200),
+                current: (4480, 1440),
+                maximum: (16384, 16384),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_every_output() {
+        let state = parse(DESKTOP_QUERY).unwrap();
+
+        let names: Vec<&str> = state.outputs.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["DVI-D-0", "HDMI-0", "DP-0", "DP-1", "DP-2", "USB-C-0"]
+        );
+        assert_eq!(
+            output(&state, "DVI-D-0").connection,
+            Connection::Disconnected
+        );
+        assert!(output(&state, "DVI-D-0").geometry.is_none());
+
+        let state = parse(LAPTOP_QUERY).unwrap();
+        assert_eq!(output(&state, "VIRTUAL1").connection, Connection::Unknown);
+    }
+
+    #[test]
+    fn parses_connected_but_disabled_outputs() {
+        let state = parse(LAPTOP_QUERY).unwrap();
+        let hdmi = output(&state, "HDMI2");
+
+        assert!(hdmi.is_connected());
+        assert!(hdmi.geometry.is_none());
+        assert!(hdmi.current_mode().is_none());
+        assert_eq!(hdmi.modes.len(), 2);
+        assert!(hdmi.modes[0].is_preferred);
+
+        let state = parse(LAPTOP_DOCKED_VERBOSE).unwrap();
+        let dp = output(&state, "DP-2");
+
+        assert!(dp.is_connected());
+        assert!(dp.geometry.is_none());
+        assert_eq!(dp.crtc, None);
+        assert_eq!(dp.modes.len(), 2);
+    }
+
+    #[test]
+    fn parses_geometry_rotation_and_physical_size() {
+        let state = parse(DESKTOP_QUERY).unwrap();
+
+        let hdmi = output(&state, "HDMI-0");
+        assert!(!hdmi.is_primary);
+        assert_eq!(
+            hdmi.geometry,
+            Some(Geometry {
+                width: 1080,
+                height: 1920,
+                x: 0,
+                y: 0
+            })
+        );
+        assert_eq!(hdmi.rotation, Rotation::Left);
+        assert_eq!(hdmi.reflection, Reflection::Normal);
+        assert_eq!(hdmi.physical_size, Some((531, 299)));
+
+        let dp = output(&state, "DP-0");
+        assert!(dp.is_primary);
+        assert_eq!(dp.rotation, Rotation::Normal);
+        assert_eq!(dp.geometry.unwrap().x, 1080);
+
+        let state = parse(LAPTOP_QUERY).unwrap();
+        let hdmi = output(&state, "HDMI1");
+        assert_eq!(hdmi.rotation, Rotation::Normal);
+        assert_eq!(hdmi.reflection, Reflection::X);
+        assert_eq!(hdmi.geometry.unwrap().width, 3840);
+    }
+
+    #[test]
+    fn parses_modes_with_every_refresh_rate() {
+        let state = parse(DESKTOP_QUERY).unwrap();
+        let dp = output(&state, "DP-0");
+
+        assert_eq!(dp.modes.len(), 10);
+        assert_eq!(
+            dp.modes[0],
+            Mode {
+                name: "3840x2160".to_string(),
+                width: 3840,
+                height: 2160,
+                refresh: 60.0,
+                is_current: false,
+                is_preferred: true,
+            }
+        );
+        assert_eq!(
+            dp.current_mode(),
+            Some(&Mode {
+                name: "3840x2160".to_string(),
+                width: 3840,
+                height: 2160,
+                refresh: 144.0,
+                is_current: true,
+                is_preferred: false,
+            })
+        );
+
+        let interlaced: Vec<&Mode> = output(&state, "HDMI-0")
+            .modes
+            .iter()
+            .filter(|mode| mode.name == "1920x1080i")
+            .collect();
+        assert_eq!(interlaced.len(), 3);
+        assert_eq!(interlaced[1].refresh, 59.94);
+    }
+
+    #[test]
+    fn parses_verbose_modes() {
+        let state = parse(LAPTOP_DOCKED_VERBOSE).unwrap();
+        let edp = output(&state, "eDP-1");
+
+        assert_eq!(edp.mode_id.as_deref(), Some("0x4a"));
+        assert_eq!(edp.modes.len(), 4);
+        assert_eq!(
+            edp.modes[0],
+            Mode {
+                name: "1920x1080".to_string(),
+                width: 1920,
+                height: 1080,
+                refresh: 60.02,
+                is_current: true,
+                is_preferred: true,
+            }
+        );
+        assert_eq!(edp.modes[1].refresh, 48.02);
+        assert!(!edp.modes[1].is_current && !edp.modes[1].is_preferred);
+
+        let dp = output(&state, "DP-1");
+        assert_eq!(dp.modes[1].refresh, 120.0);
+        assert_eq!(dp.geometry.unwrap().x, 1920);
+    }
+
+    #[test]
+    fn parses_verbose_properties() {
+        let state = parse(LAPTOP_DOCKED_VERBOSE).unwrap();
+        let edp = output(&state, "eDP-1");
+
+        assert_eq!(edp.crtc, Some(0));
+        assert_eq!(edp.crtcs, vec![0, 1, 2, 3]);
+        assert_eq!(output(&state, "DP-1").crtc, Some(1));
+
+        let edid = edp.property("EDID").unwrap();
+        assert_eq!(edid.value, "");
+        assert_eq!(edid.details.len(), 8);
+        assert!(edid.details[0].starts_with("00ffffffffffff00"));
+
+        let backlight = edp.property("BACKLIGHT").unwrap();
+        assert_eq!(backlight.value, "400");
+        assert_eq!(backlight.details, vec!["range: (0, 937)"]);
+
+        let transform = edp.property("Transform").unwrap();
+        assert_eq!(transform.details.len(), 3);
+
+        assert!(output(&state, "HDMI-1").property("EDID").is_none());
+    }
+}
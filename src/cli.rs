@@ -0,0 +1,575 @@
+use std::{io::Write, path::Path};
+
+use clap::{crate_description, crate_version, value_parser, Arg, ArgAction, ArgMatches, Command};
+
+use crate::{
+    app::AppResult,
+    backend::DisplayBackend,
+    profile::{self, Profile},
+    screen::{self, place_relative, Location, Rotation, Screen},
+};
+
+const LOCATIONS: [(&str, Location); 5] = [
+    ("right-of", Location::RIGHT),
+    ("left-of", Location::LEFT),
+    ("above", Location::UP),
+    ("below", Location::DOWN),
+    ("same-as", Location::SAME),
+];
+
+pub fn command() -> Command {
+    Command::new("tuix")
+        .about(crate_description!())
+        .version(crate_version!())
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .global(true)
+                .help("Preview the changes without applying them")
+                .action(ArgAction::SetTrue),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List the connected outputs")
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print the outputs, their modes and EDID as JSON")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("apply")
+                .about("Apply a saved profile")
+                .arg(Arg::new("profile").required(true)),
+        )
+        .subcommand(
+            Command::new("save")
+                .about("Save the current layout as a profile")
+                .arg(Arg::new("name").required(true)),
+        )
+        .subcommand(set_command())
+}
+
+fn set_command() -> Command {
+    let mut command = Command::new("set")
+        .about("Change the configuration of an output")
+        .arg(Arg::new("output").required(true))
+        .arg(
+            Arg::new("mode")
+                .long("mode")
+                .value_name("MODE")
+                .help("Resolution, e.g. 2560x1440"),
+        )
+        .arg(
+            Arg::new("rate")
+                .long("rate")
+                .value_name("RATE")
+                .value_parser(value_parser!(f32))
+                .help("Refresh rate in Hz, the closest one is picked"),
+        )
+        .arg(
+            Arg::new("scale")
+                .long("scale")
+                .value_name("SCALE")
+                .value_parser(value_parser!(f32))
+                .help("Scale of the output, e.g. 2 on a HiDPI screen"),
+        )
+        .arg(
+            Arg::new("pos")
+                .long("pos")
+                .value_name("XxY")
+                .help("Absolute position, e.g. 1920x0"),
+        )
+        .arg(
+            Arg::new("off")
+                .long("off")
+                .help("Disable the output")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["mode", "rate", "scale", "pos"]),
+        );
+
+    for (name, _) in LOCATIONS {
+        let others: Vec<&str> = LOCATIONS
+            .iter()
+            .map(|(other, _)| *other)
+            .filter(|other| *other != name)
+            .chain(["pos", "off"])
+            .collect();
+
+        command = command.arg(
+            Arg::new(name)
+                .long(name)
+                .value_name("OUTPUT")
+                .help(match name {
+                    "same-as" => "Mirror another output".to_string(),
+                    _ => format!("Place the output {} another one", name.replace('-', " ")),
+                })
+                .conflicts_with_all(others),
+        );
+    }
+
+    command
+}
+
+/// Runs a subcommand without the TUI, writing its result to `out`.
+pub fn run(
+    matches: &ArgMatches,
+    backend: &dyn DisplayBackend,
+    profiles_dir: Option<&Path>,
+    out: &mut dyn Write,
+) -> AppResult<()> {
+    let profiles_dir = || profiles_dir.ok_or("Can not find the configuration directory");
+
+    match matches.subcommand() {
+        Some(("list", args)) if args.get_flag("json") => {
+            writeln!(out, "{}", screen::to_json(&backend.get_screens()?)?)?;
+            Ok(())
+        }
+
+        Some(("list", _)) => {
+            for screen in backend.get_screens()? {
+                writeln!(out, "{}", describe(&screen))?;
+            }
+            Ok(())
+        }
+
+        Some(("save", args)) => {
+            let name = args.get_one::<String>("name").unwrap();
+            Profile::from_screens(name, &backend.get_screens()?).save(profiles_dir()?)?;
+            writeln!(out, "Profile {} saved", name)?;
+            Ok(())
+        }
+
+        Some(("apply", args)) => {
+            let name = args.get_one::<String>("profile").unwrap();
+            profile::validate_name(name)?;
+            let path = profiles_dir()?.join(format!("{}.toml", name));
+            if !path.exists() {
+                return Err(format!("Profile {} not found", name).into());
+            }
+
+            let mut screens = backend.get_screens()?;
+            Profile::load(&path)?.restore(&mut screens)?;
+            apply(matches, backend, &screens, out)
+        }
+
+        Some(("set", args)) => {
+            let mut screens = backend.get_screens()?;
+            set(args, &mut screens)?;
+            apply(matches, backend, &screens, out)
+        }
+
+        _ => Ok(()),
+    }
+}
+
+fn apply(
+    matches: &ArgMatches,
+    backend: &dyn DisplayBackend,
+    screens: &[Screen],
+    out: &mut dyn Write,
+) -> AppResult<()> {
+    if !screens.iter().any(|screen| screen.has_pending_changes()) {
+        return Ok(());
+    }
+
+    if matches.get_flag("dry-run") {
+        writeln!(out, "{}", backend.preview(screens))?;
+        return Ok(());
+    }
+
+    backend.apply(screens)?;
+    backend.keep()
+}
+
+/// How far, in Hz, the refresh rate of a mode can be from the requested one.
+const RATE_TOLERANCE: f32 = 0.5;
+
+/// Sets the pending changes of the output requested by the `set` subcommand.
+fn set(args: &ArgMatches, screens: &mut [Screen]) -> AppResult<()> {
+    let output = args.get_one::<String>("output").unwrap();
+    let index = find(screens, output)?;
+
+    if args.get_flag("off") {
+        return screen::disable(screens, index);
+    }
+
+    let screen = &mut screens[index];
+    screen.new_enabled = Some(true);
+
+    let name = args.get_one::<String>("mode");
+    let rate = args.get_one::<f32>("rate");
+    if name.is_some() || rate.is_some() {
+        let name = name
+            .or(screen.mode().map(|mode| &mode.name))
+            .ok_or(format!("{} has no current mode", screen.name))?
+            .clone();
+
+        let modes = screen.modes.iter().filter(|mode| mode.name == name);
+        let mode = match rate {
+            // The closest refresh rate, like xrandr, as the real ones are rarely round
+            Some(rate) => modes
+                .filter(|mode| (mode.refresh - rate).abs() <= RATE_TOLERANCE)
+                .min_by(|a, b| {
+                    (a.refresh - rate)
+                        .abs()
+                        .total_cmp(&(b.refresh - rate).abs())
+                }),
+            // Without rate, keep the current one or use the preferred one
+            None => modes.max_by_key(|mode| (mode.is_current, mode.is_preferred)),
+        }
+        .ok_or(match rate {
+            Some(rate) => format!(
+                "{} does not support the mode {} at {} Hz",
+                output, name, rate
+            ),
+            None => format!("{} does not support the mode {}", output, name),
+        })?;
+        screen.new_mode = Some(mode.clone());
+    }
+
+    if let Some(scale) = args.get_one::<f32>("scale") {
+        let (min, max) = screen::SCALE_RANGE;
+        if !(min..=max).contains(scale) {
+            return Err(format!("Invalid scale {}, expected {} to {}", scale, min, max).into());
+        }
+        screen::set_scale(screens, index, *scale);
+    }
+
+    let screen = &mut screens[index];
+    if let Some(position) = args.get_one::<String>("pos") {
+        screen.new_position = Some(parse_position(position)?);
+    }
+
+    for (arg, location) in LOCATIONS {
+        if let Some(anchor) = args.get_one::<String>(arg) {
+            let anchor = find(screens, anchor)?;
+            if !screens[anchor].is_enabled() {
+                return Err(format!("{} is disabled", screens[anchor].name).into());
+            }
+            match location {
+                Location::SAME => screen::mirror(screens, index, anchor)?,
+                _ => place_relative(screens, index, anchor, location),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn find(screens: &[Screen], name: &str) -> AppResult<usize> {
+    screens
+        .iter()
+        .position(|screen| screen.name == name)
+        .ok_or(format!("Output {} not found", name).into())
+}
+
+fn parse_position(position: &str) -> AppResult<(u16, u16)> {
+    position
+        .split_once('x')
+        .and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?)))
+        .ok_or(format!("Invalid position {}, expected XxY", position).into())
+}
+
+/// One line summary of a screen, e.g. `DP-1 2560x1440+1920+0 143.97Hz primary`.
+fn describe(screen: &Screen) -> String {
+    let mut line = screen.name.clone();
+
+    if screen.enabled {
+        let (width, height) = screen.size();
+        let (x, y) = screen.position;
+        line.push_str(&format!(" {}x{}+{}+{}", width, height, x, y));
+
+        if let Some(mode) = screen.mode() {
+            line.push_str(&format!(" {:.2}Hz", mode.refresh));
+        }
+    } else {
+        line.push_str(" off");
+    }
+
+    if screen.is_primary {
+        line.push_str(" primary");
+    }
+
+    if screen.rotation != Rotation::Normal {
+        line.push_str(&format!(" rotate {}", screen.rotation));
+    }
+
+    if screen.scale != 1.0 {
+        line.push_str(&format!(" scale {}", screen.scale));
+    }
+
+    if let Some(name) = screen.edid.as_ref().and_then(|edid| edid.name.as_ref()) {
+        line.push_str(&format!(" ({})", name));
+    }
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{backend::mock::MockBackend, screen::Mode};
+
+    fn mode(width: u16, height: u16, refresh: f32, is_current: bool) -> Mode {
+        Mode {
+            name: format!("{}x{}", width, height),
+            width,
+            height,
+            refresh,
+            is_current,
+            is_preferred: refresh == 60.0,
+        }
+    }
+
+    fn backend() -> MockBackend {
+        MockBackend::new(vec![
+            Screen {
+                name: "eDP-1".to_string(),
+                is_primary: true,
+                enabled: true,
+                resolution: (1920, 1080),
+                modes: vec![mode(1920, 1080, 60.0, true)],
+                ..Default::default()
+            },
+            Screen {
+                name: "HDMI-1".to_string(),
+                enabled: false,
+                resolution: (2560, 1440),
+                modes: vec![
+                    mode(2560, 1440, 60.0, false),
+                    mode(2560, 1440, 144.0, false),
+                    mode(1920, 1080, 60.0, false),
+                ],
+                ..Default::default()
+            },
+        ])
+    }
+
+    fn run_args(backend: &MockBackend, dir: &Path, args: &[&str]) -> AppResult<String> {
+        let matches = command().try_get_matches_from([&["tuix"], args].concat())?;
+        let mut out = Vec::new();
+        run(&matches, backend, Some(dir), &mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn lists_the_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = run_args(&backend(), dir.path(), &["list"]).unwrap();
+
+        assert_eq!(output, "eDP-1 1920x1080+0+0 60.00Hz primary\nHDMI-1 off\n");
+    }
+
+    #[test]
+    fn lists_the_outputs_as_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = run_args(&backend(), dir.path(), &["list", "--json"]).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(json[0]["name"], "eDP-1");
+        assert_eq!(json[0]["is_primary"], true);
+        assert_eq!(json[1]["enabled"], false);
+        assert_eq!(json[1]["modes"][1]["refresh"], 144.0);
+        assert_eq!(json[1]["edid"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn sets_the_mode_and_location() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = backend();
+
+        run_args(
+            &backend,
+            dir.path(),
+            &[
+                "set",
+                "HDMI-1",
+                "--left-of",
+                "eDP-1",
+                "--mode",
+                "2560x1440",
+                "--rate",
+                "144",
+            ],
+        )
+        .unwrap();
+
+        let screens = backend.get_screens().unwrap();
+        assert!(screens[1].enabled);
+        assert_eq!(screens[1].position, (0, 0));
+        assert_eq!(screens[1].mode().unwrap().refresh, 144.0);
+        assert_eq!(screens[0].position, (2560, 0));
+    }
+
+    #[test]
+    fn picks_the_closest_refresh_rate() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = backend();
+        let mut screens = backend.get_screens().unwrap();
+        screens[1].modes = vec![
+            mode(2560, 1440, 59.951, false),
+            mode(2560, 1440, 143.6, false),
+            mode(2560, 1440, 143.972, false),
+        ];
+        backend.set_screens(screens);
+
+        let args = [
+            "set",
+            "HDMI-1",
+            "--right-of",
+            "eDP-1",
+            "--mode",
+            "2560x1440",
+        ];
+        run_args(
+            &backend,
+            dir.path(),
+            &[&args[..], &["--rate", "144"]].concat(),
+        )
+        .unwrap();
+        let screens = backend.get_screens().unwrap();
+        assert_eq!(screens[1].mode().unwrap().refresh, 143.972);
+
+        let error = run_args(
+            &backend,
+            dir.path(),
+            &[&args[..], &["--rate", "120"]].concat(),
+        );
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "HDMI-1 does not support the mode 2560x1440 at 120 Hz"
+        );
+    }
+
+    #[test]
+    fn scales_an_output_and_shifts_its_neighbours() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = backend();
+
+        run_args(
+            &backend,
+            dir.path(),
+            &["set", "HDMI-1", "--right-of", "eDP-1"],
+        )
+        .unwrap();
+        run_args(&backend, dir.path(), &["set", "eDP-1", "--scale", "1.5"]).unwrap();
+
+        let screens = backend.get_screens().unwrap();
+        assert_eq!(screens[0].scale, 1.5);
+        assert_eq!(screens[0].size(), (1280, 720));
+        assert_eq!(screens[1].position, (1280, 0));
+    }
+
+    #[test]
+    fn mirrors_an_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = backend();
+
+        run_args(
+            &backend,
+            dir.path(),
+            &["set", "HDMI-1", "--same-as", "eDP-1"],
+        )
+        .unwrap();
+
+        let screens = backend.get_screens().unwrap();
+        assert!(screens[1].enabled);
+        assert_eq!(screens[1].position, (0, 0));
+        assert_eq!(screens[1].resolution, (1920, 1080));
+        assert_eq!(screens[1].scale, 1.0);
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = backend();
+
+        for args in [
+            vec!["set", "DP-9", "--pos", "0x0"],
+            vec!["set", "HDMI-1", "--mode", "800x600"],
+            vec!["set", "HDMI-1", "--mode", "1920x1080", "--rate", "144"],
+            vec!["set", "HDMI-1", "--pos", "left"],
+            vec!["set", "HDMI-1", "--pos", "0x0", "--right-of", "eDP-1"],
+            vec!["set", "HDMI-1", "--same-as", "eDP-1", "--left-of", "eDP-1"],
+            vec!["set", "HDMI-1", "--off", "--mode", "1920x1080"],
+            vec!["set", "eDP-1", "--off"],
+            vec!["set", "eDP-1", "--scale", "8"],
+        ] {
+            assert!(run_args(&backend, dir.path(), &args).is_err(), "{:?}", args);
+        }
+
+        assert!(backend.applied().is_empty());
+    }
+
+    #[test]
+    fn saves_and_applies_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = backend();
+
+        run_args(&backend, dir.path(), &["set", "HDMI-1", "--pos", "1920x0"]).unwrap();
+        assert_eq!(
+            run_args(&backend, dir.path(), &["save", "docked"]).unwrap(),
+            "Profile docked saved\n"
+        );
+
+        run_args(&backend, dir.path(), &["set", "HDMI-1", "--off"]).unwrap();
+        assert!(!backend.get_screens().unwrap()[1].enabled);
+
+        run_args(&backend, dir.path(), &["apply", "docked"]).unwrap();
+        let screens = backend.get_screens().unwrap();
+        assert!(screens[1].enabled);
+        assert_eq!(screens[1].position, (1920, 0));
+
+        assert!(run_args(&backend, dir.path(), &["apply", "missing"]).is_err());
+    }
+
+    #[test]
+    fn hands_the_primary_role_over_when_turning_it_off() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = backend();
+
+        run_args(&backend, dir.path(), &["set", "HDMI-1", "--pos", "1920x0"]).unwrap();
+        run_args(&backend, dir.path(), &["set", "eDP-1", "--off"]).unwrap();
+
+        let screens = backend.get_screens().unwrap();
+        assert!(!screens[0].enabled);
+        assert!(!screens[0].is_primary);
+        assert!(screens[1].is_primary);
+    }
+
+    #[test]
+    fn rejects_invalid_profile_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let profiles = dir.path().join("profiles");
+        fs::create_dir_all(&profiles).unwrap();
+        fs::write(dir.path().join("outside.toml"), "outputs = []").unwrap();
+
+        for name in ["", "../outside", ".hidden", "a\\b"] {
+            let error = run_args(&backend(), &profiles, &["apply", name]).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("Invalid profile name: {:?}", name)
+            );
+        }
+    }
+
+    #[test]
+    fn dry_run_prints_the_preview() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = backend();
+
+        let output = run_args(
+            &backend,
+            dir.path(),
+            &["set", "HDMI-1", "--right-of", "eDP-1", "--dry-run"],
+        )
+        .unwrap();
+
+        assert_eq!(output, "mock apply HDMI-1\n");
+        assert!(backend.applied().is_empty());
+    }
+}
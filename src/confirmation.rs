@@ -0,0 +1,93 @@
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, BorderType, Borders, Clear, Padding, Paragraph},
+    Frame,
+};
+
+use crate::screen::Screen;
+
+/// How long the user has to confirm an applied configuration.
+pub const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The dialog shown after applying a configuration, reverting it unless confirmed.
+#[derive(Debug)]
+pub struct Confirmation {
+    /// The screens as they were before applying.
+    pub previous: Vec<Screen>,
+    pub deadline: Instant,
+}
+
+impl Confirmation {
+    pub fn new(previous: Vec<Screen>) -> Self {
+        Self {
+            previous,
+            deadline: Instant::now() + TIMEOUT,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    /// Remaining seconds, rounded up.
+    pub fn remaining(&self) -> u64 {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0)
+    }
+
+    pub fn render(&self, frame: &mut Frame) {
+        let block = confirmation_rect(6, frame.size());
+
+        let text = vec![
+            Line::from("Keep this configuration?").bold(),
+            Line::from(format!("Reverting in {}s", self.remaining())),
+            Line::from(""),
+            Line::from("y: keep  n: revert").dark_gray(),
+        ];
+
+        let popup = Paragraph::new(text).alignment(Alignment::Center).block(
+            Block::default()
+                .padding(Padding::horizontal(2))
+                .title(" Confirm ")
+                .title_style(Style::default().bold().fg(Color::Yellow))
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL)
+                .style(Style::default())
+                .border_type(BorderType::Thick)
+                .border_style(Style::default().fg(Color::Yellow)),
+        );
+
+        frame.render_widget(Clear, block);
+        frame.render_widget(popup, block);
+    }
+}
+
+pub fn confirmation_rect(height: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Min(1),
+                Constraint::Length(height),
+                Constraint::Min(1),
+            ]
+            .as_ref(),
+        )
+        .split(r);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Min(1),
+                Constraint::Length(40),
+                Constraint::Min(1),
+            ]
+            .as_ref(),
+        )
+        .split(popup_layout[1])[1]
+}
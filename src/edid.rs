@@ -0,0 +1,212 @@
+use std::{fs, path::Path};
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+use crate::app::AppResult;
+
+const HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+
+/// The identity of a monitor, decoded from the base block of its EDID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edid {
+    /// Three letters PNP id, e.g. `DEL`.
+    pub manufacturer: String,
+    pub product: u16,
+    pub serial: u32,
+    /// Serial number descriptor, more reliable than `serial` when present.
+    pub serial_number: Option<String>,
+    /// Monitor name descriptor, e.g. `DELL U2719D`.
+    pub name: Option<String>,
+    pub week: u8,
+    pub year: u16,
+    /// Physical size in millimeters.
+    pub physical_size: (u32, u32),
+}
+
+impl Edid {
+    pub fn parse(bytes: &[u8]) -> AppResult<Self> {
+        if bytes.len() < 128 || bytes[..8] != HEADER {
+            return Err("Invalid EDID header".into());
+        }
+
+        if bytes[..128].iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+            return Err("Invalid EDID checksum".into());
+        }
+
+        let id = u16::from_be_bytes([bytes[8], bytes[9]]);
+        let manufacturer = [(id >> 10) & 0x1f, (id >> 5) & 0x1f, id & 0x1f]
+            .iter()
+            .map(|letter| (b'A' - 1 + *letter as u8) as char)
+            .collect();
+
+        let mut name = None;
+        let mut serial_number = None;
+
+        // The size of the preferred timing is in mm, the basic one in cm
+        let mut physical_size = (bytes[21] as u32 * 10, bytes[22] as u32 * 10);
+
+        for (index, descriptor) in bytes[54..126].chunks(18).enumerate() {
+            if descriptor[0] != 0 || descriptor[1] != 0 {
+                let width = descriptor[12] as u32 | ((descriptor[14] as u32 >> 4) << 8);
+                let height = descriptor[13] as u32 | ((descriptor[14] as u32 & 0x0f) << 8);
+                if index == 0 && width > 0 && height > 0 {
+                    physical_size = (width, height);
+                }
+                continue;
+            }
+
+            let text = String::from_utf8_lossy(&descriptor[5..18]);
+            let text = text.split('\n').next().unwrap_or_default().trim();
+            let text = (!text.is_empty()).then(|| text.to_string());
+
+            match descriptor[3] {
+                0xfc => name = text,
+                0xff => serial_number = text,
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            manufacturer,
+            product: u16::from_le_bytes([bytes[10], bytes[11]]),
+            serial: u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]),
+            serial_number,
+            name,
+            week: bytes[16],
+            year: bytes[17] as u16 + 1990,
+            physical_size,
+        })
+    }
+
+    /// Parses the hex dump printed by `xrandr --verbose`.
+    pub fn from_hex(hex: &str) -> AppResult<Self> {
+        let hex: String = hex.split_whitespace().collect();
+
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2).unwrap_or_default(), 16))
+            .collect::<Result<Vec<u8>, _>>()?;
+
+        Self::parse(&bytes)
+    }
+
+    /// Identifies the monitor regardless of the connector it is plugged in.
+    pub fn fingerprint(&self) -> String {
+        let serial = match &self.serial_number {
+            Some(serial_number) => serial_number.clone(),
+            None => format!("{:08x}", self.serial),
+        };
+
+        format!("{}-{:04x}-{}", self.manufacturer, self.product, serial)
+    }
+}
+
+impl Serialize for Edid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut edid = serializer.serialize_struct("Edid", 9)?;
+        edid.serialize_field("fingerprint", &self.fingerprint())?;
+        edid.serialize_field("manufacturer", &self.manufacturer)?;
+        edid.serialize_field("product", &self.product)?;
+        edid.serialize_field("serial", &self.serial)?;
+        edid.serialize_field("serial_number", &self.serial_number)?;
+        edid.serialize_field("name", &self.name)?;
+        edid.serialize_field("week", &self.week)?;
+        edid.serialize_field("year", &self.year)?;
+        edid.serialize_field("physical_size", &self.physical_size)?;
+        edid.end()
+    }
+}
+
+/// Reads the EDID of an output from `<root>/*/edid`, `root` being usually `/sys/class/drm`.
+///
+/// DRM connectors are named like `card0-HDMI-A-1` while xrandr calls them
+/// `HDMI-1` or `HDMI1` depending on the driver, so the names are compared
+/// without the card prefix, dashes and the `A` connector type suffix.
+pub fn read_sysfs(root: &Path, output: &str) -> Option<Edid> {
+    let normalize = |name: &str| name.to_lowercase().replace("-a-", "-").replace('-', "");
+    let output = normalize(output);
+
+    fs::read_dir(root).ok()?.flatten().find_map(|entry| {
+        let file_name = entry.file_name();
+        let (_, connector) = file_name.to_str()?.split_once('-')?;
+
+        if normalize(connector) != output {
+            return None;
+        }
+
+        let bytes = fs::read(entry.path().join("edid")).ok()?;
+        Edid::parse(&bytes).ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELL: &str = "00ffffffffffff0010acc4a0424a4b4c0c1e0104a53c22783aee95a3544c99260f505400000001010101010101010101010101010101023a801871382d40582c450058542100001e000000fc0044454c4c205532373139440a20000000ff00354b43304630330a202020202000000010000000000000000000000000000000d1";
+
+    const LAPTOP_PANEL: &str = "00ffffffffffff0006af3d5700000000011d0104a51f11783aee95a3544c99260f505400000001010101010101010101010101010101023a801871382d40582c450036aa1000001e000000fc000a202020202020202020202020000000fe000a20202020202020202020202000000010000000000000000000000000000000ec";
+
+    #[test]
+    fn decodes_the_monitor_identity() {
+        let edid = Edid::from_hex(DELL).unwrap();
+
+        assert_eq!(
+            edid,
+            Edid {
+                manufacturer: "DEL".to_string(),
+                product: 0xa0c4,
+                serial: 0x4c4b4a42,
+                serial_number: Some("5KC0F03".to_string()),
+                name: Some("DELL U2719D".to_string()),
+                week: 12,
+                year: 2020,
+                physical_size: (600, 340),
+            }
+        );
+        assert_eq!(edid.fingerprint(), "DEL-a0c4-5KC0F03");
+    }
+
+    #[test]
+    fn falls_back_to_the_numeric_serial() {
+        let edid = Edid::from_hex(LAPTOP_PANEL).unwrap();
+
+        assert_eq!(edid.manufacturer, "AUO");
+        assert_eq!(edid.name, None);
+        assert_eq!(edid.serial_number, None);
+        assert_eq!(edid.physical_size, (310, 170));
+        assert_eq!(edid.fingerprint(), "AUO-573d-00000000");
+    }
+
+    #[test]
+    fn rejects_corrupted_edids() {
+        let mut corrupted = DELL.to_string();
+        corrupted.replace_range(40..42, "ff");
+        assert!(Edid::from_hex(&corrupted).is_err());
+
+        assert!(Edid::from_hex(&DELL[2..]).is_err());
+        assert!(Edid::from_hex("00ff").is_err());
+        assert!(Edid::from_hex("zz").is_err());
+    }
+
+    #[test]
+    fn reads_the_edid_from_sysfs() {
+        let root = tempfile::tempdir().unwrap();
+        let bytes: Vec<u8> = (0..DELL.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&DELL[i..i + 2], 16).unwrap())
+            .collect();
+
+        fs::create_dir(root.path().join("card1-eDP-1")).unwrap();
+        fs::write(root.path().join("card1-eDP-1").join("edid"), []).unwrap();
+        fs::create_dir(root.path().join("card1-HDMI-A-1")).unwrap();
+        fs::write(root.path().join("card1-HDMI-A-1").join("edid"), &bytes).unwrap();
+
+        for name in ["HDMI-1", "HDMI1", "HDMI-A-1"] {
+            let edid = read_sysfs(root.path(), name).unwrap();
+            assert_eq!(edid.name.as_deref(), Some("DELL U2719D"));
+        }
+        assert!(read_sysfs(root.path(), "eDP-1").is_none());
+        assert!(read_sysfs(root.path(), "DP-1").is_none());
+    }
+}
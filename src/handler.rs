@@ -3,35 +3,66 @@ use std::sync::mpsc::Sender;
 use crate::{
     app::{App, AppResult},
     event::Event,
-    notification::Notification,
-    xrandr::Xrandr,
+    notification::{Notification, NotificationLevel},
+    screen::Location,
+};
+use ratatui::crossterm::event::{
+    KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
 };
-use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 pub fn handle_key_events(
     key_event: KeyEvent,
     app: &mut App,
     sender: Sender<Event>,
 ) -> AppResult<()> {
+    if app.confirmation.is_some() {
+        match key_event.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                if let Err(e) = app.keep_configuration() {
+                    Notification::send(e.to_string(), NotificationLevel::Error, sender)?;
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                if let Err(e) = app.revert_configuration() {
+                    Notification::send(e.to_string(), NotificationLevel::Error, sender)?;
+                }
+            }
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    if app.mode_picker.show {
+        match key_event.code {
+            KeyCode::Char('j') | KeyCode::Down => app.mode_picker.scroll_down(),
+            KeyCode::Char('k') | KeyCode::Up => app.mode_picker.scroll_up(),
+            KeyCode::Enter => app.pick_mode(),
+            KeyCode::Esc | KeyCode::Char('m') => app.mode_picker.close(),
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    if app.profiles.show {
+        return handle_profiles_key_events(key_event, app, sender);
+    }
+
     match key_event.code {
         KeyCode::Char('q') => {
             app.quit();
         }
 
-        KeyCode::Char('c') | KeyCode::Char('C') => {
-            if key_event.modifiers == KeyModifiers::CONTROL {
-                app.quit();
-            }
+        KeyCode::Char('c') | KeyCode::Char('C') if key_event.modifiers == KeyModifiers::CONTROL => {
+            app.quit();
         }
 
         KeyCode::Esc => {
             if app.help.show_help {
                 app.help.show_help = false;
             } else {
-                app.screens.iter_mut().for_each(|screen| {
-                    screen.new_position = None;
-                    screen.location = None;
-                });
+                app.screens
+                    .iter_mut()
+                    .for_each(|screen| screen.clear_pending_changes());
             }
         }
 
@@ -39,73 +70,204 @@ pub fn handle_key_events(
             app.help.show_help = true;
         }
 
-        KeyCode::Enter => {
-            let primary_screen_name = {
-                let screen = app.screens.iter().find(|screen| screen.is_primary);
-                screen.unwrap().name.as_ref()
-            };
+        KeyCode::Tab => {
+            app.select_next();
+        }
 
-            if let Some(screen) = app.screens.iter().find(|screen| !screen.is_primary) {
-                if let Some(location) = &screen.location {
-                    if let Err(e) =
-                        Xrandr::arrange(location, primary_screen_name, screen.name.as_ref())
-                    {
-                        Notification::send(
-                            e.to_string(),
-                            crate::notification::NotificationLevel::Error,
-                            sender,
-                        )?;
-                    }
-                }
+        KeyCode::BackTab => {
+            app.select_previous();
+        }
+
+        KeyCode::Char('a') => {
+            app.cycle_anchor();
+        }
+
+        KeyCode::Enter
+            if app
+                .screens
+                .iter()
+                .any(|screen| screen.has_pending_changes()) =>
+        {
+            match app.apply() {
+                Ok(()) if app.dry_run => Notification::send(
+                    "Dry run, the changes were not applied".to_string(),
+                    NotificationLevel::Info,
+                    sender,
+                )?,
+                Ok(()) => {}
+                Err(e) => Notification::send(e.to_string(), NotificationLevel::Error, sender)?,
             }
         }
 
-        KeyCode::Char('l') => {
-            let mut screens_iter = app.screens.iter_mut();
+        KeyCode::Char('m') => {
+            app.open_mode_picker();
+        }
 
-            let primary_screen = screens_iter.find(|screen| screen.is_primary).unwrap();
+        KeyCode::Char('p') => {
+            if let Err(e) = app.profiles.open(&app.screens) {
+                Notification::send(e.to_string(), NotificationLevel::Error, sender)?;
+            }
+        }
 
-            if let Some(screen) = screens_iter.find(|screen| !screen.is_primary) {
-                screen.new_position = Some((primary_screen.resolution.0, 0));
-                primary_screen.new_position = Some((0, 0));
-                screen.location = Some(crate::xrandr::Location::RIGHT);
+        KeyCode::Char('P') => {
+            if let Err(e) = app.set_primary_selected() {
+                Notification::send(e.to_string(), NotificationLevel::Warning, sender)?;
             }
         }
 
-        KeyCode::Char('k') => {
-            let mut screens_iter = app.screens.iter_mut();
-            let primary_screen = screens_iter.find(|screen| screen.is_primary).unwrap();
+        KeyCode::Char('+') => {
+            app.scale_selected(1.0);
+        }
 
-            if let Some(screen) = screens_iter.find(|screen| !screen.is_primary) {
-                screen.new_position = Some((0, 0));
-                primary_screen.new_position = Some((0, screen.resolution.1));
-                screen.location = Some(crate::xrandr::Location::UP);
+        KeyCode::Char('-') => {
+            app.scale_selected(-1.0);
+        }
+
+        KeyCode::Char('M') => {
+            if let Err(e) = app.mirror_selected() {
+                Notification::send(e.to_string(), NotificationLevel::Warning, sender)?;
             }
         }
 
-        KeyCode::Char('j') => {
-            let mut screens_iter = app.screens.iter_mut();
-            let primary_screen = screens_iter.find(|screen| screen.is_primary).unwrap();
+        KeyCode::Char('r') => {
+            app.rotate_selected();
+        }
+
+        KeyCode::Char('x') => {
+            app.reflect_selected();
+        }
 
-            if let Some(screen) = screens_iter.find(|screen| !screen.is_primary) {
-                screen.new_position = Some((0, primary_screen.resolution.1));
-                primary_screen.new_position = Some((0, 0));
-                screen.location = Some(crate::xrandr::Location::DOWN);
+        KeyCode::Char('e') => {
+            if let Err(e) = app.toggle_selected() {
+                Notification::send(e.to_string(), NotificationLevel::Warning, sender)?;
             }
         }
 
+        KeyCode::Char('l') => {
+            app.place(Location::RIGHT);
+        }
+
+        KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down => {
+            let step = if key_event.modifiers.contains(KeyModifiers::SHIFT) {
+                100
+            } else {
+                10
+            };
+
+            let delta = match key_event.code {
+                KeyCode::Left => (-step, 0),
+                KeyCode::Right => (step, 0),
+                KeyCode::Up => (0, -step),
+                _ => (0, step),
+            };
+
+            app.nudge_selected(delta);
+        }
+
+        KeyCode::Char('c') => {
+            app.align_selected();
+        }
+
+        KeyCode::Char('k') => {
+            app.place(Location::UP);
+        }
+
+        KeyCode::Char('j') => {
+            app.place(Location::DOWN);
+        }
+
         KeyCode::Char('h') => {
-            let mut screens_iter = app.screens.iter_mut();
-            let primary_screen = screens_iter.find(|screen| screen.is_primary).unwrap();
+            app.place(Location::LEFT);
+        }
+
+        _ => {}
+    }
+    Ok(())
+}
 
-            if let Some(screen) = screens_iter.find(|screen| !screen.is_primary) {
-                screen.new_position = Some((0, 0));
-                primary_screen.new_position = Some((screen.resolution.0, 0));
-                screen.location = Some(crate::xrandr::Location::LEFT);
+fn handle_profiles_key_events(
+    key_event: KeyEvent,
+    app: &mut App,
+    sender: Sender<Event>,
+) -> AppResult<()> {
+    // Typing the name of a new profile
+    if let Some(name) = app.profiles.name_input.as_mut() {
+        match key_event.code {
+            KeyCode::Char(c) => name.push(c),
+            KeyCode::Backspace => {
+                name.pop();
+            }
+            KeyCode::Esc => app.profiles.name_input = None,
+            KeyCode::Enter => {
+                let name = name.clone();
+                app.profiles.name_input = None;
+                match app.save_profile(&name) {
+                    Ok(()) => Notification::send(
+                        format!("Profile {} saved", name),
+                        NotificationLevel::Info,
+                        sender,
+                    )?,
+                    Err(e) => Notification::send(e.to_string(), NotificationLevel::Error, sender)?,
+                }
             }
+            _ => {}
         }
+        return Ok(());
+    }
 
+    match key_event.code {
+        KeyCode::Char('j') | KeyCode::Down => app.profiles.scroll_down(),
+        KeyCode::Char('k') | KeyCode::Up => app.profiles.scroll_up(),
+        KeyCode::Char('s') => app.profiles.name_input = Some(String::new()),
+        KeyCode::Char('d') => {
+            if let Err(e) = app.profiles.delete_selected() {
+                Notification::send(e.to_string(), NotificationLevel::Error, sender)?;
+            }
+        }
+        KeyCode::Enter => {
+            let name = app.profiles.selected().map(|profile| profile.name.clone());
+            match app.restore_selected_profile() {
+                Ok(()) => {
+                    if app.dry_run {
+                        Notification::send(
+                            "Dry run, the changes were not applied".to_string(),
+                            NotificationLevel::Info,
+                            sender,
+                        )?;
+                    } else if let Some(name) = name {
+                        Notification::send(
+                            format!("Profile {} restored", name),
+                            NotificationLevel::Info,
+                            sender,
+                        )?;
+                    }
+                    app.profiles.close();
+                }
+                Err(e) => Notification::send(e.to_string(), NotificationLevel::Error, sender)?,
+            }
+        }
+        KeyCode::Esc | KeyCode::Char('p') => app.profiles.close(),
         _ => {}
     }
+
+    Ok(())
+}
+
+pub fn handle_mouse_events(mouse_event: MouseEvent, app: &mut App) -> AppResult<()> {
+    // Popups are keyboard only
+    if app.confirmation.is_some() || app.mode_picker.show || app.profiles.show || app.help.show_help
+    {
+        return Ok(());
+    }
+
+    let (column, row) = (mouse_event.column, mouse_event.row);
+
+    match mouse_event.kind {
+        MouseEventKind::Down(MouseButton::Left) => app.press_mouse(column, row),
+        MouseEventKind::Drag(MouseButton::Left) => app.drag_mouse(column, row),
+        MouseEventKind::Up(MouseButton::Left) => app.release_mouse(column, row),
+        _ => {}
+    }
+
     Ok(())
 }
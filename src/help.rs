@@ -23,10 +23,26 @@ impl Default for Help {
             state,
             keys: vec![
                 (Cell::from("Esc").bold(), "Dismiss the move"),
-                (Cell::from("h or left").bold(), "Move left"),
-                (Cell::from("j or Down").bold(), "Move down"),
-                (Cell::from("k or Up").bold(), "Move up"),
-                (Cell::from("l or right").bold(), "Move right"),
+                (Cell::from("Tab").bold(), "Select the next screen"),
+                (Cell::from("Shift+Tab").bold(), "Select the previous screen"),
+                (Cell::from("a").bold(), "Change the anchor screen"),
+                (Cell::from("h").bold(), "Move left of the anchor"),
+                (Cell::from("j").bold(), "Move below the anchor"),
+                (Cell::from("k").bold(), "Move above the anchor"),
+                (Cell::from("l").bold(), "Move right of the anchor"),
+                (
+                    Cell::from("Arrows").bold(),
+                    "Nudge by 10px, 100px with Shift",
+                ),
+                (Cell::from("c").bold(), "Align with the anchor"),
+                (Cell::from("M").bold(), "Mirror the anchor"),
+                (Cell::from("+/-").bold(), "Scale the screen up or down"),
+                (Cell::from("e").bold(), "Enable or disable the screen"),
+                (Cell::from("m").bold(), "Pick the mode of the screen"),
+                (Cell::from("P").bold(), "Make the screen primary"),
+                (Cell::from("r").bold(), "Rotate the screen"),
+                (Cell::from("x").bold(), "Reflect the screen"),
+                (Cell::from("p").bold(), "Show the saved profiles"),
                 (Cell::from("Enter").bold(), "Apply the changes"),
                 (Cell::from("?").bold(), "Show help"),
             ],
@@ -56,13 +72,7 @@ impl Help {
     }
     pub fn scroll_up(&mut self) {
         let i = match self.state.selected() {
-            Some(i) => {
-                if i > 1 {
-                    i - 1
-                } else {
-                    0
-                }
-            }
+            Some(i) => i.saturating_sub(1),
             None => 1,
         };
         *self.state.offset_mut() = i;
@@ -70,7 +80,7 @@ impl Help {
     }
 
     pub fn render(&mut self, frame: &mut Frame) {
-        let block = help_rect(frame.size());
+        let block = help_rect(self.keys.len() as u16 + 6, frame.size());
 
         self.block_height = block.height as usize;
         let widths = [Constraint::Length(20), Constraint::Max(40)];
@@ -100,14 +110,14 @@ impl Help {
     }
 }
 
-pub fn help_rect(r: Rect) -> Rect {
+pub fn help_rect(height: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints(
             [
-                Constraint::Percentage(35),
-                Constraint::Length(13),
-                Constraint::Percentage(35),
+                Constraint::Min(1),
+                Constraint::Length(height),
+                Constraint::Min(1),
             ]
             .as_ref(),
         )
@@ -10,6 +10,18 @@ pub mod handler;
 
 pub mod help;
 
-pub mod xrandr;
+pub mod mode_picker;
+
+pub mod backend;
+
+pub mod screen;
 
 pub mod notification;
+
+pub mod profile;
+
+pub mod edid;
+
+pub mod confirmation;
+
+pub mod cli;
@@ -2,12 +2,29 @@ use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
 use std::io;
 use tuix::app::{App, AppResult};
+use tuix::backend;
+use tuix::cli;
 use tuix::event::{Event, EventHandler};
-use tuix::handler::handle_key_events;
+use tuix::handler::{handle_key_events, handle_mouse_events};
+use tuix::profile;
 use tuix::tui::Tui;
 
 fn main() -> AppResult<()> {
-    let mut app = App::new();
+    let args = cli::command().get_matches();
+
+    let backend = backend::detect();
+
+    if args.subcommand().is_some() {
+        return cli::run(
+            &args,
+            backend.as_ref(),
+            profile::profiles_dir().as_deref(),
+            &mut io::stdout(),
+        );
+    }
+
+    let mut app = App::new(backend);
+    app.dry_run = args.get_flag("dry-run");
 
     let backend = CrosstermBackend::new(io::stderr());
     let terminal = Terminal::new(backend)?;
@@ -22,7 +39,7 @@ fn main() -> AppResult<()> {
             Event::Key(key_event) => {
                 handle_key_events(key_event, &mut app, tui.events.sender.clone())?
             }
-            Event::Mouse(_) => {}
+            Event::Mouse(mouse_event) => handle_mouse_events(mouse_event, &mut app)?,
             Event::Resize(_, _) => {}
             Event::Notification(notification) => {
                 app.notifications.push(notification);
@@ -0,0 +1,135 @@
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Style, Stylize},
+    widgets::{Block, BorderType, Borders, Clear, Padding, Row, Table, TableState},
+    Frame,
+};
+
+use crate::screen::{Mode, Screen};
+
+#[derive(Debug, Default)]
+pub struct ModePicker {
+    pub show: bool,
+    /// Name of the output the modes belong to, which keeps pointing to it
+    /// when another one is unplugged.
+    pub screen: String,
+    pub modes: Vec<Mode>,
+    state: TableState,
+}
+
+impl ModePicker {
+    pub fn open(&mut self, screen: &Screen) {
+        self.screen = screen.name.clone();
+        self.modes = screen.modes.clone();
+
+        // Start on the pending mode, or the current one
+        let selected = screen
+            .new_mode
+            .as_ref()
+            .and_then(|mode| self.modes.iter().position(|m| m == mode))
+            .or(self.modes.iter().position(|m| m.is_current))
+            .unwrap_or(0);
+
+        self.state = TableState::new().with_selected(Some(selected));
+        self.show = true;
+    }
+
+    pub fn close(&mut self) {
+        self.show = false;
+    }
+
+    pub fn selected_mode(&self) -> Option<&Mode> {
+        self.state.selected().and_then(|i| self.modes.get(i))
+    }
+
+    pub fn scroll_down(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.modes.len() => i + 1,
+            Some(i) => i,
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn scroll_up(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) => i.saturating_sub(1),
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn render(&mut self, screen: &Screen, frame: &mut Frame) {
+        let block = mode_picker_rect(self.modes.len() as u16 + 2, frame.size());
+
+        let widths = [
+            Constraint::Length(14),
+            Constraint::Length(12),
+            Constraint::Min(10),
+        ];
+
+        let rows: Vec<Row> = self
+            .modes
+            .iter()
+            .map(|mode| {
+                let mut markers = Vec::new();
+                if mode.is_current {
+                    markers.push("current");
+                }
+                if mode.is_preferred {
+                    markers.push("preferred");
+                }
+
+                Row::new(vec![
+                    mode.name.clone(),
+                    format!("{:.2} Hz", mode.refresh),
+                    markers.join(", "),
+                ])
+                .style(Style::default().fg(Color::White))
+            })
+            .collect();
+
+        let table = Table::new(rows, widths)
+            .highlight_style(Style::default().bg(Color::DarkGray))
+            .block(
+                Block::default()
+                    .padding(Padding::horizontal(2))
+                    .title(format!(" Modes of {} ", screen.name))
+                    .title_style(Style::default().bold().fg(Color::Green))
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::ALL)
+                    .style(Style::default())
+                    .border_type(BorderType::Thick)
+                    .border_style(Style::default().fg(Color::Green)),
+            );
+
+        frame.render_widget(Clear, block);
+        frame.render_stateful_widget(table, block, &mut self.state);
+    }
+}
+
+pub fn mode_picker_rect(height: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Min(1),
+                Constraint::Length(height),
+                Constraint::Min(1),
+            ]
+            .as_ref(),
+        )
+        .split(r);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Min(1),
+                Constraint::Length(60),
+                Constraint::Min(1),
+            ]
+            .as_ref(),
+        )
+        .split(popup_layout[1])[1]
+}
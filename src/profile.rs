@@ -0,0 +1,695 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, BorderType, Borders, Clear, Padding, Paragraph, Row, Table, TableState},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::AppResult,
+    screen::{Reflection, Rotation, Screen},
+};
+
+/// A saved configuration of every output, stored as `<name>.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(skip)]
+    pub name: String,
+    pub outputs: Vec<OutputConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputConfig {
+    pub name: String,
+    /// EDID fingerprint of the monitor, preferred over the output name which
+    /// can change between docks and kernels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    pub enabled: bool,
+    #[serde(default)]
+    pub primary: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate: Option<f32>,
+    #[serde(default)]
+    pub position: (u16, u16),
+    #[serde(default)]
+    pub rotation: Rotation,
+    #[serde(default)]
+    pub reflection: Reflection,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+impl OutputConfig {
+    /// Whether the output describes the monitor plugged in the screen.
+    pub fn identifies(&self, screen: &Screen) -> bool {
+        match (&self.fingerprint, screen.fingerprint()) {
+            (Some(fingerprint), Some(other)) => *fingerprint == other,
+            _ => self.name == screen.name,
+        }
+    }
+}
+
+/// Refuses the names which would not make a file of the profiles directory.
+pub fn validate_name(name: &str) -> AppResult<()> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("Invalid profile name: {:?}", name).into());
+    }
+    Ok(())
+}
+
+/// `$XDG_CONFIG_HOME/tuix/profiles`
+pub fn profiles_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("tuix").join("profiles"))
+}
+
+impl Profile {
+    /// Captures the current configuration of the screens.
+    pub fn from_screens(name: &str, screens: &[Screen]) -> Self {
+        let outputs = screens
+            .iter()
+            .map(|screen| {
+                let mode = screen.modes.iter().find(|mode| mode.is_current);
+                OutputConfig {
+                    name: screen.name.clone(),
+                    fingerprint: screen.fingerprint(),
+                    enabled: screen.enabled,
+                    primary: screen.is_primary,
+                    mode: mode.map(|mode| mode.name.clone()),
+                    rate: mode.map(|mode| mode.refresh),
+                    position: screen.position,
+                    rotation: screen.rotation,
+                    reflection: screen.reflection,
+                    scale: screen.scale,
+                }
+            })
+            .collect();
+
+        Self {
+            name: name.to_string(),
+            outputs,
+        }
+    }
+
+    /// Pairs every screen with the output describing it, if any.
+    ///
+    /// Each output goes to one screen only, the one on the same connector
+    /// first, so identical monitors do not all get the first one.
+    fn assign(&self, screens: &[Screen]) -> Vec<Option<usize>> {
+        let mut taken = vec![false; self.outputs.len()];
+        let mut assigned = vec![None; screens.len()];
+
+        for same_connector in [true, false] {
+            for (screen, slot) in screens.iter().zip(assigned.iter_mut()) {
+                if slot.is_some() {
+                    continue;
+                }
+                *slot = self.outputs.iter().zip(&taken).position(|(output, taken)| {
+                    !taken
+                        && output.identifies(screen)
+                        && (!same_connector || output.name == screen.name)
+                });
+                if let Some(i) = *slot {
+                    taken[i] = true;
+                }
+            }
+        }
+
+        assigned
+    }
+
+    /// Whether the profile was saved with the same set of monitors.
+    pub fn matches(&self, screens: &[Screen]) -> bool {
+        self.outputs.len() == screens.len() && self.assign(screens).iter().all(Option::is_some)
+    }
+
+    pub fn load(path: &Path) -> AppResult<Self> {
+        let content = fs::read_to_string(path)?;
+        let mut profile: Profile = toml::from_str(&content)?;
+
+        profile.name = path
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        Ok(profile)
+    }
+
+    pub fn save(&self, dir: &Path) -> AppResult<()> {
+        validate_name(&self.name)?;
+
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join(format!("{}.toml", self.name)),
+            toml::to_string(self)?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Sets the pending changes of the screens so applying them restores the profile.
+    ///
+    /// Screens missing from the profile are turned off.
+    pub fn restore(&self, screens: &mut [Screen]) -> AppResult<()> {
+        let assigned = self.assign(screens);
+
+        for (i, output) in self.outputs.iter().enumerate() {
+            if output.enabled && !assigned.contains(&Some(i)) {
+                return Err(format!("{} is not connected", output.name).into());
+            }
+        }
+
+        if !self.outputs.iter().any(|output| output.enabled) {
+            return Err("The profile has no active output".into());
+        }
+
+        // Work on a copy so the screens are left untouched on error
+        let mut restored = screens.to_vec();
+
+        for (screen, output) in restored.iter_mut().zip(assigned) {
+            screen.clear_pending_changes();
+
+            let Some(output) = output.map(|i| &self.outputs[i]) else {
+                screen.new_enabled = Some(false);
+                continue;
+            };
+
+            screen.new_enabled = Some(output.enabled);
+            if !output.enabled {
+                continue;
+            }
+
+            if let Some(name) = &output.mode {
+                let mode = screen
+                    .modes
+                    .iter()
+                    .find(|mode| {
+                        &mode.name == name
+                            && output
+                                .rate
+                                .is_none_or(|rate| (mode.refresh - rate).abs() < 0.01)
+                    })
+                    .ok_or(format!(
+                        "{} does not support the mode {}",
+                        screen.name, name
+                    ))?;
+                screen.new_mode = Some(mode.clone());
+            }
+
+            screen.new_position = Some(output.position);
+            screen.new_rotation = Some(output.rotation);
+            screen.new_reflection = Some(output.reflection);
+            screen.new_primary = Some(output.primary);
+            screen.new_scale = Some(output.scale);
+        }
+
+        screens.clone_from_slice(&restored);
+
+        Ok(())
+    }
+}
+
+/// A file of the profiles directory which can not be loaded, and why.
+pub type InvalidProfile = (PathBuf, String);
+
+/// Loads every profile of `dir`, sorted by name.
+///
+/// The files which can not be loaded are skipped, and returned with the
+/// reason next to the valid profiles.
+pub fn load_profiles(dir: &Path) -> AppResult<(Vec<Profile>, Vec<InvalidProfile>)> {
+    if !dir.exists() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+
+    let mut profiles = Vec::new();
+    let mut invalid = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            match Profile::load(&path) {
+                Ok(profile) => profiles.push(profile),
+                Err(e) => invalid.push((path, e.to_string())),
+            }
+        }
+    }
+
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    invalid.sort();
+
+    Ok((profiles, invalid))
+}
+
+/// The profiles panel.
+#[derive(Debug, Default)]
+pub struct Profiles {
+    pub show: bool,
+    pub dir: Option<PathBuf>,
+    pub list: Vec<Profile>,
+    /// Files of the directory which are not valid profiles, with the reason.
+    pub invalid: Vec<InvalidProfile>,
+    /// Name of the profile being saved, while it is typed.
+    pub name_input: Option<String>,
+    state: TableState,
+}
+
+impl Profiles {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            dir,
+            ..Default::default()
+        }
+    }
+
+    fn dir(&self) -> AppResult<&Path> {
+        self.dir
+            .as_deref()
+            .ok_or("Can not find the configuration directory".into())
+    }
+
+    pub fn reload(&mut self) -> AppResult<()> {
+        (self.list, self.invalid) = load_profiles(self.dir()?)?;
+
+        let selected = match self.state.selected() {
+            _ if self.list.is_empty() => None,
+            Some(i) => Some(i.min(self.list.len() - 1)),
+            None => Some(0),
+        };
+        self.state.select(selected);
+
+        Ok(())
+    }
+
+    /// Opens the panel, selecting the profile matching the connected monitors.
+    pub fn open(&mut self, screens: &[Screen]) -> AppResult<()> {
+        self.reload()?;
+        if let Some(index) = self.list.iter().position(|p| p.matches(screens)) {
+            self.state.select(Some(index));
+        }
+        self.name_input = None;
+        self.show = true;
+        Ok(())
+    }
+
+    pub fn close(&mut self) {
+        self.name_input = None;
+        self.show = false;
+    }
+
+    pub fn selected(&self) -> Option<&Profile> {
+        self.state.selected().and_then(|i| self.list.get(i))
+    }
+
+    pub fn save(&mut self, profile: &Profile) -> AppResult<()> {
+        profile.save(self.dir()?)?;
+        self.reload()?;
+
+        let index = self.list.iter().position(|p| p.name == profile.name);
+        self.state.select(index);
+
+        Ok(())
+    }
+
+    pub fn delete_selected(&mut self) -> AppResult<()> {
+        if let Some(profile) = self.selected() {
+            fs::remove_file(self.dir()?.join(format!("{}.toml", profile.name)))?;
+            self.reload()?;
+        }
+        Ok(())
+    }
+
+    pub fn scroll_down(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.list.len() => i + 1,
+            Some(i) => i,
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn scroll_up(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) => i.saturating_sub(1),
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn render(&mut self, frame: &mut Frame) {
+        let height = (self.list.len() + self.invalid.len()).max(1) as u16 + 4;
+        let block = profiles_rect(height, frame.size());
+
+        let popup = Block::default()
+            .padding(Padding::horizontal(2))
+            .title(" Profiles ")
+            .title_style(Style::default().bold().fg(Color::Green))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .style(Style::default())
+            .border_type(BorderType::Thick)
+            .border_style(Style::default().fg(Color::Green));
+
+        let inner = popup.inner(block);
+        frame.render_widget(Clear, block);
+        frame.render_widget(popup, block);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(inner);
+
+        if self.list.is_empty() && self.invalid.is_empty() {
+            frame.render_widget(
+                Paragraph::new("No saved profile").style(Style::default().dark_gray()),
+                chunks[0],
+            );
+        } else {
+            let rows: Vec<Row> = self
+                .list
+                .iter()
+                .map(|profile| {
+                    let outputs = profile
+                        .outputs
+                        .iter()
+                        .filter(|output| output.enabled)
+                        .map(|output| output.name.as_str())
+                        .collect::<Vec<&str>>()
+                        .join(", ");
+
+                    Row::new(vec![profile.name.clone(), outputs])
+                        .style(Style::default().fg(Color::White))
+                })
+                .chain(self.invalid.iter().map(|(path, error)| {
+                    let name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    let error = error.lines().next().unwrap_or_default().to_string();
+
+                    Row::new(vec![name, error]).style(Style::default().fg(Color::Red))
+                }))
+                .collect();
+
+            let table = Table::new(rows, [Constraint::Length(20), Constraint::Min(10)])
+                .highlight_style(Style::default().bg(Color::DarkGray));
+
+            frame.render_stateful_widget(table, chunks[0], &mut self.state);
+        }
+
+        let footer = match &self.name_input {
+            Some(name) => Line::from(format!("Name: {}_", name)).yellow(),
+            None => Line::from("s: save  Enter: restore  d: delete").dark_gray(),
+        };
+        frame.render_widget(Paragraph::new(footer), chunks[1]);
+    }
+}
+
+pub fn profiles_rect(height: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Min(1),
+                Constraint::Length(height),
+                Constraint::Min(1),
+            ]
+            .as_ref(),
+        )
+        .split(r);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Min(1),
+                Constraint::Length(60),
+                Constraint::Min(1),
+            ]
+            .as_ref(),
+        )
+        .split(popup_layout[1])[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{edid::Edid, screen::Mode};
+
+    fn screen(name: &str, position: (u16, u16), refresh: f32) -> Screen {
+        Screen {
+            name: name.to_string(),
+            enabled: true,
+            resolution: (1920, 1080),
+            position,
+            modes: vec![
+                Mode {
+                    name: "1920x1080".to_string(),
+                    width: 1920,
+                    height: 1080,
+                    refresh: 60.0,
+                    is_current: refresh == 60.0,
+                    is_preferred: true,
+                },
+                Mode {
+                    name: "1920x1080".to_string(),
+                    width: 1920,
+                    height: 1080,
+                    refresh: 144.0,
+                    is_current: refresh == 144.0,
+                    is_preferred: false,
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn serializes_to_toml() {
+        let mut screens = vec![
+            screen("eDP-1", (0, 0), 60.0),
+            screen("DP-1", (1920, 0), 144.0),
+        ];
+        screens[0].is_primary = true;
+        screens[1].rotation = Rotation::Left;
+        let profile = Profile::from_screens("desk", &screens);
+
+        let toml = toml::to_string(&profile).unwrap();
+        assert_eq!(
+            toml,
+            r#"[[outputs]]
+name = "eDP-1"
+enabled = true
+primary = true
+mode = "1920x1080"
+rate = 60.0
+position = [0, 0]
+rotation = "normal"
+reflection = "normal"
+scale = 1.0
+
+[[outputs]]
+name = "DP-1"
+enabled = true
+primary = false
+mode = "1920x1080"
+rate = 144.0
+position = [1920, 0]
+rotation = "left"
+reflection = "normal"
+scale = 1.0
+"#
+        );
+
+        let mut parsed: Profile = toml::from_str(&toml).unwrap();
+        parsed.name = "desk".to_string();
+        assert_eq!(parsed, profile);
+    }
+
+    #[test]
+    fn fills_missing_fields_with_defaults() {
+        let profile: Profile = toml::from_str(
+            r#"[[outputs]]
+name = "HDMI-1"
+enabled = false
+"#,
+        )
+        .unwrap();
+
+        let output = &profile.outputs[0];
+        assert_eq!(output.mode, None);
+        assert_eq!(output.scale, 1.0);
+        assert_eq!(output.rotation, Rotation::Normal);
+    }
+
+    #[test]
+    fn restores_the_pending_configuration() {
+        let saved = vec![
+            screen("eDP-1", (2560, 0), 60.0),
+            screen("DP-1", (0, 0), 144.0),
+        ];
+        let profile = Profile::from_screens("desk", &saved);
+
+        let mut screens = vec![
+            screen("eDP-1", (0, 0), 60.0),
+            screen("DP-1", (1920, 0), 60.0),
+            screen("HDMI-1", (3840, 0), 60.0),
+        ];
+        profile.restore(&mut screens).unwrap();
+
+        assert_eq!(screens[0].pending_position(), (2560, 0));
+        assert!(screens[0].has_pending_changes());
+        assert_eq!(screens[1].pending_position(), (0, 0));
+        assert_eq!(screens[1].mode().unwrap().refresh, 144.0);
+        assert!(!screens[2].is_enabled());
+    }
+
+    #[test]
+    fn refuses_profiles_with_disconnected_outputs() {
+        let profile = Profile::from_screens("desk", &[screen("DP-1", (0, 0), 60.0)]);
+        let mut screens = vec![screen("eDP-1", (0, 0), 60.0)];
+
+        assert!(profile.restore(&mut screens).is_err());
+        assert!(!screens[0].has_pending_changes());
+    }
+
+    fn with_edid(mut screen: Screen, hex: &str) -> Screen {
+        screen.edid = Some(Edid::from_hex(hex).unwrap());
+        screen
+    }
+
+    const DELL: &str = "00ffffffffffff0010acc4a0424a4b4c0c1e0104a53c22783aee95a3544c99260f505400000001010101010101010101010101010101023a801871382d40582c450058542100001e000000fc0044454c4c205532373139440a20000000ff00354b43304630330a202020202000000010000000000000000000000000000000d1";
+
+    const BENQ: &str = "00ffffffffffff0009d12a7f45540000081c0104a5351e783aee95a3544c99260f505400000001010101010101010101010101010101023a801871382d40582c4500122c2100001e000000fc0042656e51204757323438300a20000000fe000a20202020202020202020202000000010000000000000000000000000000000ef";
+
+    #[test]
+    fn matches_monitors_by_fingerprint() {
+        let saved = vec![
+            with_edid(screen("DP-1", (0, 0), 60.0), DELL),
+            with_edid(screen("DP-2", (1920, 0), 60.0), BENQ),
+        ];
+        let profile = Profile::from_screens("desk", &saved);
+
+        // Same monitors, swapped between the connectors of another dock
+        let mut screens = vec![
+            with_edid(screen("DP-1", (0, 0), 60.0), BENQ),
+            with_edid(screen("DP-3", (1920, 0), 60.0), DELL),
+        ];
+        assert!(profile.matches(&screens));
+
+        profile.restore(&mut screens).unwrap();
+        assert_eq!(screens[0].pending_position(), (1920, 0));
+        assert_eq!(screens[1].pending_position(), (0, 0));
+
+        let other = vec![with_edid(screen("DP-1", (0, 0), 60.0), DELL)];
+        assert!(!profile.matches(&other));
+    }
+
+    #[test]
+    fn tells_identical_monitors_apart() {
+        let saved = vec![
+            with_edid(screen("DP-1", (0, 0), 60.0), DELL),
+            with_edid(screen("DP-2", (1920, 0), 144.0), DELL),
+        ];
+        let profile = Profile::from_screens("twins", &saved);
+
+        let mut screens = vec![saved[1].clone(), saved[0].clone()];
+        assert!(profile.matches(&screens));
+
+        profile.restore(&mut screens).unwrap();
+        assert_eq!(screens[0].pending_position(), (1920, 0));
+        assert_eq!(screens[0].mode().unwrap().refresh, 144.0);
+        assert_eq!(screens[1].pending_position(), (0, 0));
+        assert_eq!(screens[1].mode().unwrap().refresh, 60.0);
+
+        // On other connectors, each monitor still gets its own output
+        let mut screens = vec![
+            with_edid(screen("DP-3", (0, 0), 60.0), DELL),
+            with_edid(screen("DP-4", (0, 0), 60.0), DELL),
+        ];
+        profile.restore(&mut screens).unwrap();
+        assert_eq!(screens[0].pending_position(), (0, 0));
+        assert_eq!(screens[1].pending_position(), (1920, 0));
+    }
+
+    #[test]
+    fn selects_the_matching_profile_when_opened() {
+        let dir = tempfile::tempdir().unwrap();
+        let docked = vec![
+            with_edid(screen("eDP-1", (0, 0), 60.0), BENQ),
+            with_edid(screen("DP-1", (1920, 0), 60.0), DELL),
+        ];
+        Profile::from_screens("a-laptop", &docked[..1])
+            .save(dir.path())
+            .unwrap();
+        Profile::from_screens("b-docked", &docked)
+            .save(dir.path())
+            .unwrap();
+
+        let mut profiles = Profiles::new(Some(dir.path().to_path_buf()));
+        profiles.open(&docked).unwrap();
+        assert_eq!(profiles.selected().unwrap().name, "b-docked");
+
+        profiles.open(&docked[..1]).unwrap();
+        assert_eq!(profiles.selected().unwrap().name, "a-laptop");
+    }
+
+    #[test]
+    fn saves_and_loads_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let profile = Profile::from_screens("work", &[screen("eDP-1", (0, 0), 60.0)]);
+
+        profile.save(dir.path()).unwrap();
+        Profile::from_screens("home", &[]).save(dir.path()).unwrap();
+
+        let (profiles, invalid) = load_profiles(dir.path()).unwrap();
+        assert!(invalid.is_empty());
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].name, "home");
+        assert_eq!(profiles[1], profile);
+    }
+
+    #[test]
+    fn skips_the_corrupt_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let docked = vec![screen("eDP-1", (0, 0), 60.0)];
+        Profile::from_screens("work", &docked)
+            .save(dir.path())
+            .unwrap();
+        fs::write(dir.path().join("broken.toml"), "[[outputs]\nname = ").unwrap();
+
+        let (profiles, invalid) = load_profiles(dir.path()).unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].name, "work");
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].0, dir.path().join("broken.toml"));
+
+        let mut panel = Profiles::new(Some(dir.path().to_path_buf()));
+        panel.open(&docked).unwrap();
+        assert_eq!(panel.selected().unwrap().name, "work");
+        assert_eq!(panel.invalid, invalid);
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for name in ["", "../escape", ".hidden"] {
+            assert!(Profile::from_screens(name, &[]).save(dir.path()).is_err());
+        }
+    }
+}
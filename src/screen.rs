@@ -0,0 +1,669 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{app::AppResult, edid::Edid};
+
+/// The serialized fields form the stable schema of `tuix list --json`,
+/// the pending changes are left out.
+#[derive(Debug, Clone, Serialize)]
+pub struct Screen {
+    pub name: String,
+    pub is_primary: bool,
+    pub enabled: bool,
+    /// Size of the current mode, before rotation.
+    pub resolution: (u16, u16),
+    pub position: (u16, u16),
+    pub modes: Vec<Mode>,
+    /// Physical size in millimeters, when known.
+    pub physical_size: Option<(u32, u32)>,
+    pub rotation: Rotation,
+    pub reflection: Reflection,
+    /// Logical pixels per screen pixel: a scale of 2 makes a 3840x2160 mode
+    /// take the room of a 1920x1080 one in the layout.
+    pub scale: f32,
+    pub edid: Option<Edid>,
+    #[serde(skip)]
+    pub new_position: Option<(u16, u16)>,
+    #[serde(skip)]
+    pub location: Option<Location>,
+    #[serde(skip)]
+    pub relative_to: Option<String>,
+    #[serde(skip)]
+    pub new_enabled: Option<bool>,
+    #[serde(skip)]
+    pub new_mode: Option<Mode>,
+    #[serde(skip)]
+    pub new_rotation: Option<Rotation>,
+    #[serde(skip)]
+    pub new_reflection: Option<Reflection>,
+    #[serde(skip)]
+    pub new_primary: Option<bool>,
+    #[serde(skip)]
+    pub new_scale: Option<f32>,
+}
+
+/// Serializes the current configuration of the screens, see [`Screen`].
+pub fn to_json(screens: &[Screen]) -> AppResult<String> {
+    Ok(serde_json::to_string_pretty(screens)?)
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            is_primary: false,
+            enabled: false,
+            resolution: (0, 0),
+            position: (0, 0),
+            modes: Vec::new(),
+            physical_size: None,
+            rotation: Rotation::default(),
+            reflection: Reflection::default(),
+            scale: 1.0,
+            edid: None,
+            new_position: None,
+            location: None,
+            relative_to: None,
+            new_enabled: None,
+            new_mode: None,
+            new_rotation: None,
+            new_reflection: None,
+            new_primary: None,
+            new_scale: None,
+        }
+    }
+}
+
+impl Screen {
+    /// Identifies the monitor plugged in the output, see [`Edid::fingerprint`].
+    pub fn fingerprint(&self) -> Option<String> {
+        self.edid.as_ref().map(|edid| edid.fingerprint())
+    }
+
+    pub fn pending_position(&self) -> (u16, u16) {
+        self.new_position.unwrap_or(self.position)
+    }
+
+    /// The room taken in the layout: the mode size, rotated and divided by the scale.
+    pub fn size(&self) -> (u16, u16) {
+        let (width, height) = match &self.new_mode {
+            Some(mode) => (mode.width, mode.height),
+            None => self.resolution,
+        };
+
+        let scale = self.pending_scale();
+        let (width, height) = if scale > 0.0 && scale != 1.0 {
+            (
+                (width as f32 / scale).round() as u16,
+                (height as f32 / scale).round() as u16,
+            )
+        } else {
+            (width, height)
+        };
+
+        if self.pending_rotation().is_sideways() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    pub fn pending_rotation(&self) -> Rotation {
+        self.new_rotation.unwrap_or(self.rotation)
+    }
+
+    pub fn pending_reflection(&self) -> Reflection {
+        self.new_reflection.unwrap_or(self.reflection)
+    }
+
+    pub fn pending_primary(&self) -> bool {
+        self.new_primary.unwrap_or(self.is_primary)
+    }
+
+    pub fn pending_scale(&self) -> f32 {
+        self.new_scale.unwrap_or(self.scale)
+    }
+
+    /// The pending mode, or the current one.
+    pub fn mode(&self) -> Option<&Mode> {
+        self.new_mode
+            .as_ref()
+            .or(self.modes.iter().find(|mode| mode.is_current))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.new_enabled.unwrap_or(self.enabled)
+    }
+
+    pub fn has_pending_changes(&self) -> bool {
+        self.location.is_some()
+            || self.pending_position() != self.position
+            || self
+                .new_enabled
+                .is_some_and(|enabled| enabled != self.enabled)
+            || self.new_mode.as_ref().is_some_and(|mode| !mode.is_current)
+            || self.pending_rotation() != self.rotation
+            || self.pending_reflection() != self.reflection
+            || self.pending_primary() != self.is_primary
+            || self.pending_scale() != self.scale
+    }
+
+    /// Sets the pending changes bringing the screen back to the `previous` configuration.
+    pub fn revert_to(&mut self, previous: &Screen) {
+        self.clear_pending_changes();
+        self.new_enabled = Some(previous.enabled);
+        self.new_position = Some(previous.position);
+        self.new_mode = previous.modes.iter().find(|mode| mode.is_current).cloned();
+        self.new_rotation = Some(previous.rotation);
+        self.new_reflection = Some(previous.reflection);
+        self.new_primary = Some(previous.is_primary);
+        self.new_scale = Some(previous.scale);
+    }
+
+    pub fn clear_pending_changes(&mut self) {
+        self.new_position = None;
+        self.location = None;
+        self.relative_to = None;
+        self.new_enabled = None;
+        self.new_mode = None;
+        self.new_rotation = None;
+        self.new_reflection = None;
+        self.new_primary = None;
+        self.new_scale = None;
+    }
+}
+
+/// Distance, in pixels, from which a nudged screen is pulled to an edge or a centre.
+pub const SNAP_DISTANCE: i32 = 32;
+
+fn pending_positions(screens: &[Screen]) -> Vec<(i32, i32)> {
+    screens
+        .iter()
+        .map(|screen| {
+            let (x, y) = screen.pending_position();
+            (x as i32, y as i32)
+        })
+        .collect()
+}
+
+/// Sets the pending positions of every enabled screen, shifted so the layout starts at (0, 0).
+fn set_positions(screens: &mut [Screen], positions: Vec<(i32, i32)>) {
+    let enabled_positions = || {
+        screens
+            .iter()
+            .zip(positions.iter())
+            .filter(|(screen, _)| screen.is_enabled())
+            .map(|(_, position)| *position)
+    };
+    let min_x = enabled_positions().map(|p| p.0).min().unwrap_or(0);
+    let min_y = enabled_positions().map(|p| p.1).min().unwrap_or(0);
+
+    for (screen, (x, y)) in screens.iter_mut().zip(positions) {
+        if screen.is_enabled() {
+            screen.new_position = Some(((x - min_x) as u16, (y - min_y) as u16));
+        }
+    }
+}
+
+/// Places the screen `index` next to the screen `anchor` and recomputes the
+/// pending positions of every enabled screen so the layout starts at (0, 0).
+pub fn place_relative(screens: &mut [Screen], index: usize, anchor: usize, location: Location) {
+    if index == anchor
+        || index >= screens.len()
+        || anchor >= screens.len()
+        || !screens[index].is_enabled()
+        || !screens[anchor].is_enabled()
+    {
+        return;
+    }
+
+    let mut positions = pending_positions(screens);
+
+    let (anchor_x, anchor_y) = positions[anchor];
+    let (anchor_width, anchor_height) = screens[anchor].size();
+    let (anchor_width, anchor_height) = (anchor_width as i32, anchor_height as i32);
+    let (width, height) = screens[index].size();
+    let (width, height) = (width as i32, height as i32);
+
+    positions[index] = match location {
+        Location::RIGHT => (anchor_x + anchor_width, anchor_y),
+        Location::LEFT => (anchor_x - width, anchor_y),
+        Location::UP => (anchor_x, anchor_y - height),
+        Location::DOWN => (anchor_x, anchor_y + anchor_height),
+        Location::SAME => (anchor_x, anchor_y),
+    };
+
+    set_positions(screens, positions);
+
+    let anchor_name = screens[anchor].name.clone();
+    let screen = &mut screens[index];
+    screen.location = Some(location);
+    screen.relative_to = Some(anchor_name);
+}
+
+/// Turns the screen `index` off, handing the primary role over to another
+/// enabled screen.
+pub fn disable(screens: &mut [Screen], index: usize) -> AppResult<()> {
+    if screens[index].is_enabled() && screens.iter().filter(|s| s.is_enabled()).count() == 1 {
+        return Err("Can not disable the last active output".into());
+    }
+
+    let screen = &mut screens[index];
+    screen.new_enabled = Some(false);
+    screen.new_position = None;
+    screen.location = None;
+    screen.relative_to = None;
+
+    if screen.pending_primary() {
+        screen.new_primary = Some(false);
+        if let Some(other) = screens.iter_mut().find(|s| s.is_enabled()) {
+            other.new_primary = Some(true);
+        }
+    }
+
+    Ok(())
+}
+
+/// The spans `(start, length)` of the enabled screens but `index`, along the x (0) or y (1) axis.
+fn spans(
+    screens: &[Screen],
+    positions: &[(i32, i32)],
+    index: usize,
+    axis: usize,
+) -> Vec<(i32, i32)> {
+    screens
+        .iter()
+        .zip(positions.iter())
+        .enumerate()
+        .filter(|(i, (screen, _))| *i != index && screen.is_enabled())
+        .map(|(_, (screen, position))| {
+            let size = screen.size();
+            if axis == 0 {
+                (position.0, size.0 as i32)
+            } else {
+                (position.1, size.1 as i32)
+            }
+        })
+        .collect()
+}
+
+/// Moves the screen `index` by `delta` pixels, along one axis.
+///
+/// Moving toward an edge or a centre of another enabled screen stops on it
+/// when it is closer than the step, or than [`SNAP_DISTANCE`].
+pub fn nudge(screens: &mut [Screen], index: usize, delta: (i32, i32)) {
+    if index >= screens.len() || !screens[index].is_enabled() {
+        return;
+    }
+
+    let mut positions = pending_positions(screens);
+    let (width, height) = screens[index].size();
+    let (x, y) = positions[index];
+
+    let dx = snap(
+        x,
+        width as i32,
+        delta.0,
+        &spans(screens, &positions, index, 0),
+    );
+    let dy = snap(
+        y,
+        height as i32,
+        delta.1,
+        &spans(screens, &positions, index, 1),
+    );
+    positions[index] = (x + dx, y + dy);
+
+    set_positions(screens, positions);
+
+    let screen = &mut screens[index];
+    screen.location = None;
+    screen.relative_to = None;
+}
+
+pub const SCALE_STEP: f32 = 0.25;
+pub const SCALE_RANGE: (f32, f32) = (0.5, 3.0);
+
+/// Sets the pending scale of the screen `index`, within [`SCALE_RANGE`].
+///
+/// The screens right of or below it are shifted by the change of its size,
+/// so they stay next to it without overlapping or leaving a gap.
+pub fn set_scale(screens: &mut [Screen], index: usize, scale: f32) {
+    if index >= screens.len() || !screens[index].is_enabled() {
+        return;
+    }
+
+    let mut positions = pending_positions(screens);
+    let (x, y) = positions[index];
+    let (width, height) = screens[index].size();
+
+    screens[index].new_scale = Some(scale.clamp(SCALE_RANGE.0, SCALE_RANGE.1));
+
+    let (new_width, new_height) = screens[index].size();
+    let (right, bottom) = (x + width as i32, y + height as i32);
+    let (dx, dy) = (
+        new_width as i32 - width as i32,
+        new_height as i32 - height as i32,
+    );
+
+    for (other, position) in positions.iter_mut().enumerate() {
+        if other == index {
+            continue;
+        }
+        if position.0 >= right {
+            position.0 += dx;
+        }
+        if position.1 >= bottom {
+            position.1 += dy;
+        }
+    }
+
+    set_positions(screens, positions);
+}
+
+/// Moves the screen `index` to `position`, e.g. while it is dragged.
+///
+/// With a `snap_distance`, the screen is then pulled to the closest edge or
+/// centre of another enabled screen within that distance, on each axis.
+pub fn move_to(
+    screens: &mut [Screen],
+    index: usize,
+    position: (i32, i32),
+    snap_distance: Option<(i32, i32)>,
+) {
+    if index >= screens.len() || !screens[index].is_enabled() {
+        return;
+    }
+
+    let mut positions = pending_positions(screens);
+    let (x, y) = position;
+
+    positions[index] = match snap_distance {
+        Some((distance_x, distance_y)) => {
+            let (width, height) = screens[index].size();
+            let closest = |start: i32, length: i32, distance: i32, axis: usize| {
+                alignments(start, length, &spans(screens, &positions, index, axis))
+                    .filter(|offset| offset.abs() <= distance)
+                    .min_by_key(|offset| offset.abs())
+                    .unwrap_or(0)
+            };
+            (
+                x + closest(x, width as i32, distance_x, 0),
+                y + closest(y, height as i32, distance_y, 1),
+            )
+        }
+        None => position,
+    };
+
+    set_positions(screens, positions);
+
+    let screen = &mut screens[index];
+    screen.location = None;
+    screen.relative_to = None;
+}
+
+/// The offsets aligning an edge or the centre of a span `(start, length)`
+/// with the edges or the centre of the `others` spans.
+fn alignments(start: i32, length: i32, others: &[(i32, i32)]) -> impl Iterator<Item = i32> + '_ {
+    others.iter().flat_map(move |(other, other_length)| {
+        let (end, other_end) = (start + length, other + other_length);
+        [
+            other - start,
+            other_end - start,
+            other - end,
+            other_end - end,
+            (2 * other + other_length - 2 * start - length) / 2,
+        ]
+    })
+}
+
+/// The move of a span `(start, length)` by `delta`, shortened to reach the
+/// closest alignment with one of the `others` spans in the same direction.
+fn snap(start: i32, length: i32, delta: i32, others: &[(i32, i32)]) -> i32 {
+    if delta == 0 {
+        return 0;
+    }
+
+    alignments(start, length, others)
+        .filter(|offset| offset.signum() == delta.signum())
+        .filter(|offset| offset.abs() <= delta.abs().max(SNAP_DISTANCE))
+        .min_by_key(|offset| offset.abs())
+        .unwrap_or(delta)
+}
+
+/// Aligns the screen `index` with the screen `anchor`, cycling between start,
+/// centre and end: top, centre and bottom when they are side by side, left,
+/// centre and right when they are stacked.
+pub fn align(screens: &mut [Screen], index: usize, anchor: usize) {
+    if index == anchor
+        || index >= screens.len()
+        || anchor >= screens.len()
+        || !screens[index].is_enabled()
+        || !screens[anchor].is_enabled()
+    {
+        return;
+    }
+
+    let mut positions = pending_positions(screens);
+    let (x, y) = positions[index];
+    let (anchor_x, anchor_y) = positions[anchor];
+    let (width, height) = screens[index].size();
+    let (anchor_width, anchor_height) = screens[anchor].size();
+
+    let side_by_side = x + width as i32 <= anchor_x || anchor_x + anchor_width as i32 <= x;
+
+    let (current, start, length, anchor_length) = if side_by_side {
+        (y, anchor_y, height as i32, anchor_height as i32)
+    } else {
+        (x, anchor_x, width as i32, anchor_width as i32)
+    };
+
+    let alignments = [
+        start,
+        start + (anchor_length - length) / 2,
+        start + anchor_length - length,
+    ];
+    let next = match alignments.iter().position(|a| *a == current) {
+        Some(i) => alignments[(i + 1) % alignments.len()],
+        None => alignments[0],
+    };
+
+    if side_by_side {
+        positions[index].1 = next;
+    } else {
+        positions[index].0 = next;
+    }
+
+    set_positions(screens, positions);
+
+    let screen = &mut screens[index];
+    screen.location = None;
+    screen.relative_to = None;
+}
+
+/// Mirrors the screen `index` on the screen `anchor`, or places it back right
+/// of it when it is already mirrored.
+///
+/// The mirror uses the largest mode both screens support, the anchor's one
+/// when possible, or else its preferred mode. Either way, it is scaled to
+/// the size of the anchor.
+pub fn mirror(screens: &mut [Screen], index: usize, anchor: usize) -> AppResult<()> {
+    if index == anchor || index >= screens.len() || anchor >= screens.len() {
+        return Ok(());
+    }
+
+    if !screens[index].is_enabled() || !screens[anchor].is_enabled() {
+        return Err("Can not mirror a disabled output".into());
+    }
+
+    // The location is only known until the mirror is applied, the layout tells afterwards
+    let pending = screens[index].location == Some(Location::SAME);
+    let mirrored = pending
+        || (screens[index].enabled
+            && screens[index].pending_position() == screens[anchor].pending_position()
+            && screens[index].size() == screens[anchor].size());
+
+    if mirrored {
+        let screen = &mut screens[index];
+        if pending {
+            screen.new_mode = None;
+            screen.new_scale = None;
+        }
+        place_relative(screens, index, anchor, Location::RIGHT);
+        return Ok(());
+    }
+
+    let target = &screens[anchor];
+    let target_size = target.mode().map(|mode| (mode.width, mode.height));
+    let (target_width, _) = target.size();
+    let common = |width: u16, height: u16| {
+        target
+            .modes
+            .iter()
+            .any(|mode| mode.width == width && mode.height == height)
+    };
+
+    let screen = &screens[index];
+    let mode = screen
+        .modes
+        .iter()
+        .filter(|mode| common(mode.width, mode.height))
+        .max_by(|a, b| {
+            let key = |mode: &Mode| {
+                (
+                    Some((mode.width, mode.height)) == target_size,
+                    mode.width as u32 * mode.height as u32,
+                )
+            };
+            key(a).cmp(&key(b)).then(a.refresh.total_cmp(&b.refresh))
+        })
+        .or(screen.modes.iter().find(|mode| mode.is_preferred))
+        .or(screen.mode())
+        .cloned();
+
+    // Scaled so the mirror covers the whole anchor
+    let (width, height) = mode
+        .as_ref()
+        .map_or(screen.resolution, |mode| (mode.width, mode.height));
+    let width = if screen.pending_rotation().is_sideways() {
+        height
+    } else {
+        width
+    };
+    let scale = width as f32 / target_width.max(1) as f32;
+
+    let screen = &mut screens[index];
+    screen.new_mode = mode;
+    screen.new_scale = Some(scale);
+    place_relative(screens, index, anchor, Location::SAME);
+
+    Ok(())
+}
+
+/// The screens mirrored by the screen `index`: the enabled ones before it at
+/// the same position, the first one being the source of the mirror.
+pub fn mirrored(screens: &[Screen], index: usize) -> Vec<usize> {
+    let Some(screen) = screens.get(index).filter(|screen| screen.is_enabled()) else {
+        return Vec::new();
+    };
+
+    (0..index)
+        .filter(|other| {
+            screens[*other].is_enabled()
+                && screens[*other].pending_position() == screen.pending_position()
+        })
+        .collect()
+}
+
+/// Where a screen is placed relative to another, `SAME` mirroring it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    UP,
+    DOWN,
+    RIGHT,
+    LEFT,
+    SAME,
+}
+
+/// A video mode advertised by an output, one entry per refresh rate.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Mode {
+    pub name: String,
+    pub width: u16,
+    pub height: u16,
+    pub refresh: f32,
+    pub is_current: bool,
+    pub is_preferred: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rotation {
+    #[default]
+    Normal,
+    Left,
+    Inverted,
+    Right,
+}
+
+impl Rotation {
+    pub fn next(&self) -> Self {
+        match self {
+            Rotation::Normal => Rotation::Left,
+            Rotation::Left => Rotation::Inverted,
+            Rotation::Inverted => Rotation::Right,
+            Rotation::Right => Rotation::Normal,
+        }
+    }
+
+    /// Whether the width and height of the screen are swapped.
+    pub fn is_sideways(&self) -> bool {
+        matches!(self, Rotation::Left | Rotation::Right)
+    }
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Rotation::Normal => "normal",
+            Rotation::Left => "left",
+            Rotation::Inverted => "inverted",
+            Rotation::Right => "right",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Reflection {
+    #[default]
+    Normal,
+    X,
+    Y,
+    XY,
+}
+
+impl Reflection {
+    pub fn next(&self) -> Self {
+        match self {
+            Reflection::Normal => Reflection::X,
+            Reflection::X => Reflection::Y,
+            Reflection::Y => Reflection::XY,
+            Reflection::XY => Reflection::Normal,
+        }
+    }
+}
+
+impl fmt::Display for Reflection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Reflection::Normal => "normal",
+            Reflection::X => "x",
+            Reflection::Y => "y",
+            Reflection::XY => "xy",
+        };
+        write!(f, "{}", name)
+    }
+}
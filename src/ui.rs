@@ -1,118 +1,288 @@
-use std::{cmp::Ordering, rc::Rc};
-
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Style, Stylize},
     text::Line,
-    widgets::{Block, BorderType, Borders, Paragraph, Wrap},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap},
     Frame,
 };
 
-use crate::app::App;
-
-const SCREEN_NUMBER: usize = 3;
+use crate::{
+    app::App,
+    screen::{self, Reflection, Rotation, Screen},
+};
 
 pub fn render(app: &mut App, frame: &mut Frame) {
-    if !app.screens.is_empty() {
-        let nb_blocks = SCREEN_NUMBER;
+    let mut area = frame.size();
 
+    if app.dry_run || app.screens.iter().any(|s| s.has_pending_changes()) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints({
-                (0..nb_blocks)
-                    .map(|_| Constraint::Ratio(1, nb_blocks as u32))
-                    .collect::<Vec<Constraint>>()
-            })
-            .split(frame.size());
-
-        let chunks = chunks
-            .iter()
-            .map(|chunk| {
-                Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints({
-                        (0..nb_blocks)
-                            .map(|_| Constraint::Ratio(1, nb_blocks as u32))
-                            .collect::<Vec<Constraint>>()
-                    })
-                    .split(*chunk)
-            })
-            .collect::<Vec<Rc<[Rect]>>>();
-
-        let chunks: Rc<[Rect]> = chunks
-            .into_iter()
-            .flat_map(|rect| rect.iter().cloned().collect::<Vec<Rect>>())
-            .collect();
-
-        for screen in app.screens.iter() {
-            let text = vec![
-                Line::from(screen.name.clone()),
-                Line::from(format!("{}x{}", screen.resolution.0, screen.resolution.1)),
-            ];
-
-            let paragraph = Paragraph::new(text)
+            .constraints([Constraint::Min(0), Constraint::Length(5)])
+            .split(area);
+        area = chunks[0];
+
+        let title = if app.dry_run {
+            " Preview (dry run) "
+        } else {
+            " Preview "
+        };
+
+        frame.render_widget(
+            Paragraph::new(app.backend.preview(&app.screens))
+                .wrap(Wrap { trim: false })
                 .block(
                     Block::new()
                         .borders(Borders::ALL)
-                        .border_style(Style::default())
-                        .border_type(BorderType::default()),
-                )
-                .style(Style::new().white())
-                .centered()
-                .wrap(Wrap { trim: true });
-
-            if screen.is_primary {
-                let paragraph = paragraph.style(Style::default().blue());
-                frame.render_widget(paragraph, chunks[chunks.len() / 2]);
-            } else {
-                let (screen_x, screen_y) = {
-                    if let Some(position) = &screen.new_position {
-                        (position.0, position.1)
-                    } else {
-                        (screen.position.0, screen.position.1)
-                    }
-                };
-
-                let (primary_x, primary_y) = {
-                    let primary = app.screens.iter().find(|screen| screen.is_primary).unwrap();
-                    if let Some(postion) = &primary.new_position {
-                        (postion.0, postion.1)
-                    } else {
-                        (primary.position.0, primary.position.1)
-                    }
-                };
-
-                match screen_x.cmp(&primary_x) {
-                    Ordering::Greater => {
-                        let position = chunks.len() / 2 + 1;
-                        frame.render_widget(paragraph.clone(), chunks[position]);
-                    }
-                    Ordering::Less => {
-                        let position = chunks.len() / 2 - 1;
-                        frame.render_widget(paragraph.clone(), chunks[position]);
-                    }
-                    Ordering::Equal => {
-                        if screen_y < primary_y {
-                            let position = chunks.len() / 2 - nb_blocks;
-                            frame.render_widget(paragraph.clone(), chunks[position]);
-                        }
-
-                        if screen_y > primary_y {
-                            let position = chunks.len() / 2 + nb_blocks;
-                            frame.render_widget(paragraph.clone(), chunks[position]);
-                        }
-                    }
-                }
+                        .title(title)
+                        .border_style(Style::default().dark_gray()),
+                ),
+            chunks[1],
+        );
+    }
+
+    if !app.screens.is_empty() {
+        let (enabled, disabled): (Vec<usize>, Vec<usize>) =
+            (0..app.screens.len()).partition(|index| app.screens[*index].is_enabled());
+
+        let (layout_area, disabled_area) = if disabled.is_empty() {
+            (area, None)
+        } else {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(5)])
+                .split(area);
+            (chunks[0], Some(chunks[1]))
+        };
+
+        let viewport = Viewport::new(
+            enabled.iter().map(|index| {
+                let screen = &app.screens[*index];
+                (screen.pending_position(), screen.size())
+            }),
+            layout_area,
+        );
+
+        // The selected screen is drawn last, on top of the ones it overlaps
+        let mut order = enabled;
+        order.sort_by_key(|index| *index == app.selected);
+
+        for index in order {
+            let rect = screen_rect(&app.screens, &viewport, index);
+            frame.render_widget(Clear, rect);
+            frame.render_widget(screen_block(app, index), rect);
+        }
+
+        app.viewport = Some(viewport);
+
+        if let Some(area) = disabled_area {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints({
+                    (0..disabled.len())
+                        .map(|_| Constraint::Ratio(1, disabled.len() as u32))
+                        .collect::<Vec<Constraint>>()
+                })
+                .split(area);
+
+            for (chunk, index) in chunks.iter().zip(disabled) {
+                frame.render_widget(screen_block(app, index), *chunk);
             }
         }
     }
 
+    if app.mode_picker.show {
+        if let Some(screen) = app
+            .screens
+            .iter()
+            .find(|s| s.name == app.mode_picker.screen)
+        {
+            app.mode_picker.render(screen, frame);
+        }
+    }
+
+    if app.profiles.show {
+        app.profiles.render(frame);
+    }
+
     if app.help.show_help {
         app.help.render(frame);
     }
 
+    if let Some(confirmation) = &app.confirmation {
+        confirmation.render(frame);
+    }
+
     // Notifications
     for (index, notification) in app.notifications.iter().enumerate() {
         notification.render(index, frame);
     }
 }
+
+/// Maps the layout, in pixels, to the terminal cells of an area.
+///
+/// The layout is scaled to fit the area and centered, cells being about
+/// twice as tall as wide so the screens keep their aspect ratio.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub area: Rect,
+    /// Layout coordinates drawn at the top left corner of the area.
+    pub origin: (f64, f64),
+    /// Columns per pixel, rows per pixel being half of it.
+    pub scale: f64,
+}
+
+impl Viewport {
+    /// Fits the screens, given as `(position, size)`, in the area.
+    pub fn new(screens: impl Iterator<Item = ((u16, u16), (u16, u16))>, area: Rect) -> Self {
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (u16::MAX, u16::MAX, 0, 0);
+        for ((x, y), (width, height)) in screens {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x.saturating_add(width));
+            max_y = max_y.max(y.saturating_add(height));
+        }
+
+        if min_x >= max_x || min_y >= max_y {
+            return Self {
+                area,
+                origin: (0.0, 0.0),
+                scale: 1.0,
+            };
+        }
+
+        let (width, height) = ((max_x - min_x) as f64, (max_y - min_y) as f64);
+        let scale = (area.width as f64 / width).min(2.0 * area.height as f64 / height);
+
+        // Center the layout in the area
+        let origin = (
+            min_x as f64 - (area.width as f64 / scale - width) / 2.0,
+            min_y as f64 - (2.0 * area.height as f64 / scale - height) / 2.0,
+        );
+
+        Self {
+            area,
+            origin,
+            scale,
+        }
+    }
+
+    fn column(&self, x: f64) -> i32 {
+        self.area.x as i32 + ((x - self.origin.0) * self.scale).round() as i32
+    }
+
+    fn row(&self, y: f64) -> i32 {
+        self.area.y as i32 + ((y - self.origin.1) * self.scale / 2.0).round() as i32
+    }
+
+    /// The cells covered by a screen, at least 3x3 so it can be drawn.
+    pub fn to_rect(&self, position: (u16, u16), size: (u16, u16)) -> Rect {
+        let (x, y) = (position.0 as f64, position.1 as f64);
+        let (left, top) = (self.column(x), self.row(y));
+        let right = self.column(x + size.0 as f64).max(left + 3);
+        let bottom = self.row(y + size.1 as f64).max(top + 3);
+
+        let area = self.area;
+        let clamp_x = |column: i32| column.clamp(area.left() as i32, area.right() as i32) as u16;
+        let clamp_y = |row: i32| row.clamp(area.top() as i32, area.bottom() as i32) as u16;
+
+        let (left, right) = (clamp_x(left), clamp_x(right));
+        let (top, bottom) = (clamp_y(top), clamp_y(bottom));
+
+        Rect::new(left, top, right - left, bottom - top)
+    }
+
+    /// The layout coordinates of the top left corner of a cell.
+    pub fn to_layout(&self, column: u16, row: u16) -> (f64, f64) {
+        (
+            self.origin.0 + (column as i32 - self.area.x as i32) as f64 / self.scale,
+            self.origin.1 + 2.0 * (row as i32 - self.area.y as i32) as f64 / self.scale,
+        )
+    }
+}
+
+/// The cells of a screen, mirrors being stacked down and right of their source
+/// so each of them stays visible.
+pub fn screen_rect(screens: &[Screen], viewport: &Viewport, index: usize) -> Rect {
+    let screen = &screens[index];
+    let rect = viewport.to_rect(screen.pending_position(), screen.size());
+
+    let level = screen::mirrored(screens, index).len() as u16;
+    let area = viewport.area;
+    Rect {
+        x: (rect.x + 2 * level).min(area.right().saturating_sub(rect.width)),
+        y: (rect.y + level).min(area.bottom().saturating_sub(rect.height)),
+        ..rect
+    }
+}
+
+fn screen_block(app: &App, index: usize) -> Paragraph<'static> {
+    let screen = &app.screens[index];
+
+    let (width, height) = screen.size();
+    let mut text = vec![
+        match screen.edid.as_ref().and_then(|edid| edid.name.as_ref()) {
+            Some(model) => Line::from(format!("{} ({})", screen.name, model)),
+            None => Line::from(screen.name.clone()),
+        },
+        Line::from(format!("{}x{}", width, height)),
+    ];
+
+    if let Some(mode) = screen.mode() {
+        text.push(Line::from(format!("{:.2} Hz", mode.refresh)));
+    }
+
+    let (rotation, reflection) = (screen.pending_rotation(), screen.pending_reflection());
+    if rotation != Rotation::Normal || reflection != Reflection::Normal {
+        text.push(Line::from(format!(
+            "rotate {} / reflect {}",
+            rotation, reflection
+        )));
+    }
+
+    if screen.pending_scale() != 1.0 {
+        text.push(Line::from(format!("scale {}", screen.pending_scale())));
+    }
+
+    if !screen.is_enabled() {
+        text.push(Line::from("off").italic());
+    } else {
+        let mut tags = Vec::new();
+        if screen.pending_primary() {
+            tags.push("primary".to_string());
+        }
+        if index == app.anchor && app.screens.len() > 1 {
+            tags.push("anchor".to_string());
+        }
+        if let Some(source) = screen::mirrored(&app.screens, index).first() {
+            tags.push(format!("mirror of {}", app.screens[*source].name));
+        }
+        if !tags.is_empty() {
+            text.push(Line::from(tags.join(", ")).italic());
+        }
+    }
+
+    let (border_type, border_style) = if index == app.selected {
+        (BorderType::Thick, Style::default().yellow())
+    } else {
+        (BorderType::default(), Style::default())
+    };
+
+    let style = if !screen.is_enabled() {
+        Style::default().dark_gray()
+    } else if screen.pending_primary() {
+        Style::default().blue()
+    } else {
+        Style::new().white()
+    };
+
+    Paragraph::new(text)
+        .block(
+            Block::new()
+                .borders(Borders::ALL)
+                .border_style(border_style)
+                .border_type(border_type),
+        )
+        .style(style)
+        .centered()
+        .wrap(Wrap { trim: true })
+}
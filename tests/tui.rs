@@ -0,0 +1,1072 @@
+use std::{sync::mpsc, time::Instant};
+
+use ratatui::{
+    backend::TestBackend,
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind},
+    Terminal,
+};
+use tuix::{
+    app::App,
+    backend::mock::MockBackend,
+    handler::{handle_key_events, handle_mouse_events},
+    profile::Profiles,
+    screen::{Location, Mode, Reflection, Rotation, Screen},
+    ui,
+};
+
+fn screen(name: &str, is_primary: bool, resolution: (u16, u16), position: (u16, u16)) -> Screen {
+    Screen {
+        name: name.to_string(),
+        is_primary,
+        enabled: true,
+        resolution,
+        position,
+        ..Default::default()
+    }
+}
+
+fn disabled_screen(name: &str, resolution: (u16, u16)) -> Screen {
+    Screen {
+        enabled: false,
+        ..screen(name, false, resolution, (0, 0))
+    }
+}
+
+fn mode(width: u16, height: u16, refresh: f32, is_current: bool, is_preferred: bool) -> Mode {
+    Mode {
+        name: format!("{}x{}", width, height),
+        width,
+        height,
+        refresh,
+        is_current,
+        is_preferred,
+    }
+}
+
+fn laptop_and_external() -> Vec<Screen> {
+    vec![
+        screen("eDP-1", true, (1920, 1080), (0, 0)),
+        screen("HDMI-1", false, (2560, 1440), (1920, 0)),
+    ]
+}
+
+fn laptop_and_two_externals() -> Vec<Screen> {
+    vec![
+        screen("eDP-1", true, (1920, 1080), (0, 0)),
+        screen("HDMI-1", false, (2560, 1440), (1920, 0)),
+        screen("DP-2", false, (1920, 1080), (4480, 0)),
+    ]
+}
+
+fn start(screens: Vec<Screen>) -> (App, MockBackend) {
+    let backend = MockBackend::new(screens);
+    let mut app = App::new(Box::new(backend.clone()));
+    app.tick();
+    (app, backend)
+}
+
+fn press(app: &mut App, code: KeyCode) {
+    press_with_modifiers(app, code, KeyModifiers::NONE);
+}
+
+fn press_with_modifiers(app: &mut App, code: KeyCode, modifiers: KeyModifiers) {
+    let (sender, _receiver) = mpsc::channel();
+    handle_key_events(KeyEvent::new(code, modifiers), app, sender).unwrap();
+}
+
+fn mouse(app: &mut App, kind: MouseEventKind, column: u16, row: u16) {
+    let event = MouseEvent {
+        kind,
+        column,
+        row,
+        modifiers: KeyModifiers::NONE,
+    };
+    handle_mouse_events(event, app).unwrap();
+}
+
+fn draw(app: &mut App) -> Vec<String> {
+    let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+    terminal.draw(|frame| ui::render(app, frame)).unwrap();
+
+    let buffer = terminal.backend().buffer();
+    (0..buffer.area.height)
+        .map(|y| {
+            (0..buffer.area.width)
+                .map(|x| buffer.get(x, y).symbol())
+                .collect::<String>()
+        })
+        .collect()
+}
+
+/// Returns the (column, row) where `text` is first drawn.
+fn find(lines: &[String], text: &str) -> Option<(usize, usize)> {
+    lines.iter().enumerate().find_map(|(row, line)| {
+        line.find(text)
+            .map(|index| (line[..index].chars().count(), row))
+    })
+}
+
+#[test]
+fn selects_the_secondary_screen_relative_to_the_primary_at_startup() {
+    let (app, _) = start(laptop_and_external());
+
+    assert_eq!(app.screens[app.selected].name, "HDMI-1");
+    assert_eq!(app.screens[app.anchor].name, "eDP-1");
+}
+
+#[test]
+fn moving_left_applies_the_new_layout() {
+    let (mut app, backend) = start(laptop_and_external());
+
+    press(&mut app, KeyCode::Char('h'));
+
+    assert_eq!(app.screens[1].new_position, Some((0, 0)));
+    assert_eq!(app.screens[0].new_position, Some((2560, 0)));
+    assert!(backend.applied().is_empty());
+
+    press(&mut app, KeyCode::Enter);
+
+    let applied = backend.applied();
+    assert_eq!(applied.len(), 1);
+    let hdmi = applied[0].iter().find(|s| s.name == "HDMI-1").unwrap();
+    assert_eq!(hdmi.location, Some(Location::LEFT));
+    assert_eq!(hdmi.relative_to.as_deref(), Some("eDP-1"));
+
+    app.tick();
+    assert_eq!(app.screens[0].position, (2560, 0));
+    assert_eq!(app.screens[1].position, (0, 0));
+}
+
+#[test]
+fn moves_are_relative_to_the_anchor() {
+    let (mut app, backend) = start(laptop_and_two_externals());
+
+    // Select DP-2 and anchor it to HDMI-1
+    press(&mut app, KeyCode::Tab);
+    assert_eq!(app.screens[app.selected].name, "DP-2");
+    assert_eq!(app.screens[app.anchor].name, "eDP-1");
+    press(&mut app, KeyCode::Char('a'));
+    assert_eq!(app.screens[app.anchor].name, "HDMI-1");
+
+    press(&mut app, KeyCode::Char('k'));
+    press(&mut app, KeyCode::Enter);
+
+    let applied = backend.applied();
+    let dp = applied[0].iter().find(|s| s.name == "DP-2").unwrap();
+    assert_eq!(dp.location, Some(Location::UP));
+    assert_eq!(dp.relative_to.as_deref(), Some("HDMI-1"));
+
+    let positions: Vec<(u16, u16)> = applied[0].iter().map(|s| s.pending_position()).collect();
+    assert_eq!(positions, vec![(0, 1080), (1920, 1080), (1920, 0)]);
+}
+
+#[test]
+fn selection_wraps_around_and_never_matches_the_anchor() {
+    let (mut app, _) = start(laptop_and_two_externals());
+
+    for _ in 0..app.screens.len() {
+        press(&mut app, KeyCode::Tab);
+        assert_ne!(app.selected, app.anchor);
+    }
+    assert_eq!(app.screens[app.selected].name, "HDMI-1");
+
+    press(&mut app, KeyCode::BackTab);
+    assert_eq!(app.screens[app.selected].name, "eDP-1");
+    assert_ne!(app.selected, app.anchor);
+}
+
+#[test]
+fn escape_dismisses_the_pending_move() {
+    let (mut app, backend) = start(laptop_and_external());
+
+    press(&mut app, KeyCode::Char('j'));
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Enter);
+
+    assert!(app.screens.iter().all(|s| s.new_position.is_none()));
+    assert!(backend.applied().is_empty());
+}
+
+#[test]
+fn unplugged_screens_are_removed() {
+    let (mut app, backend) = start(laptop_and_two_externals());
+    press(&mut app, KeyCode::Tab);
+
+    backend.set_screens(laptop_and_external());
+    app.tick();
+
+    assert_eq!(app.screens.len(), 2);
+    assert!(app.selected < 2 && app.anchor < 2);
+    assert_ne!(app.selected, app.anchor);
+}
+
+#[test]
+fn quits_with_q_and_ctrl_c() {
+    let (mut app, _) = start(laptop_and_external());
+    press(&mut app, KeyCode::Char('q'));
+    assert!(!app.running);
+
+    let (mut app, _) = start(laptop_and_external());
+    press_with_modifiers(&mut app, KeyCode::Char('c'), KeyModifiers::CONTROL);
+    assert!(!app.running);
+}
+
+#[test]
+fn disables_the_selected_screen() {
+    let (mut app, backend) = start(laptop_and_two_externals());
+
+    press(&mut app, KeyCode::Char('e'));
+    assert!(!app.screens[1].is_enabled());
+    press(&mut app, KeyCode::Enter);
+
+    let applied = backend.applied();
+    assert_eq!(applied.len(), 1);
+    assert!(!applied[0][1].is_enabled());
+
+    app.tick();
+    assert!(!app.screens[1].enabled);
+    assert!(app.screens[0].enabled && app.screens[2].enabled);
+}
+
+#[test]
+fn refuses_to_disable_the_last_active_screen() {
+    let (mut app, _) = start(vec![
+        screen("eDP-1", true, (1920, 1080), (0, 0)),
+        disabled_screen("HDMI-1", (2560, 1440)),
+    ]);
+    let (sender, receiver) = mpsc::channel();
+
+    app.selected = 0;
+    handle_key_events(
+        KeyEvent::new(KeyCode::Char('e'), KeyModifiers::NONE),
+        &mut app,
+        sender,
+    )
+    .unwrap();
+
+    assert!(app.screens[0].is_enabled());
+    assert!(matches!(
+        receiver.try_recv(),
+        Ok(tuix::event::Event::Notification(_))
+    ));
+}
+
+#[test]
+fn enables_a_screen_right_of_the_layout() {
+    let (mut app, backend) = start(vec![
+        screen("eDP-1", true, (1920, 1080), (0, 0)),
+        screen("HDMI-1", false, (2560, 1440), (1920, 0)),
+        disabled_screen("DP-2", (1920, 1080)),
+    ]);
+
+    press(&mut app, KeyCode::Tab);
+    assert_eq!(app.screens[app.selected].name, "DP-2");
+    press(&mut app, KeyCode::Char('e'));
+
+    let dp = &app.screens[2];
+    assert!(dp.is_enabled());
+    assert_eq!(dp.pending_position(), (4480, 0));
+    assert_eq!(dp.location, Some(Location::RIGHT));
+    assert_eq!(dp.relative_to.as_deref(), Some("HDMI-1"));
+
+    press(&mut app, KeyCode::Enter);
+    app.tick();
+    assert!(backend.applied()[0][2].is_enabled());
+    assert!(app.screens[2].enabled);
+    assert_eq!(app.screens[2].position, (4480, 0));
+}
+
+#[test]
+fn renders_disabled_screens_apart() {
+    let (mut app, _) = start(vec![
+        screen("eDP-1", true, (1920, 1080), (0, 0)),
+        disabled_screen("HDMI-1", (2560, 1440)),
+    ]);
+
+    let lines = draw(&mut app);
+
+    let (_, edp) = find(&lines, "eDP-1").unwrap();
+    let (_, hdmi) = find(&lines, "HDMI-1").unwrap();
+    assert!(hdmi > edp);
+    assert!(find(&lines, "off").is_some());
+}
+
+#[test]
+fn picks_a_mode_for_the_selected_screen() {
+    let mut screens = laptop_and_external();
+    screens[1].modes = vec![
+        mode(2560, 1440, 59.95, true, true),
+        mode(2560, 1440, 143.97, false, false),
+        mode(1920, 1080, 60.0, false, false),
+    ];
+    let (mut app, backend) = start(screens);
+
+    press(&mut app, KeyCode::Char('m'));
+    assert!(app.mode_picker.show);
+
+    let lines = draw(&mut app);
+    assert!(find(&lines, "Modes of HDMI-1").is_some());
+    assert!(find(&lines, "143.97 Hz").is_some());
+    assert!(find(&lines, "current, preferred").is_some());
+
+    // Moves in the popup don't move the screen
+    press(&mut app, KeyCode::Char('j'));
+    assert!(app.screens[1].new_position.is_none());
+    press(&mut app, KeyCode::Enter);
+    assert!(!app.mode_picker.show);
+    assert_eq!(
+        app.screens[1].new_mode,
+        Some(mode(2560, 1440, 143.97, false, false))
+    );
+
+    press(&mut app, KeyCode::Enter);
+    app.tick();
+    assert!(backend.applied()[0][1].has_pending_changes());
+    assert!(app.screens[1].modes[1].is_current);
+}
+
+#[test]
+fn picking_a_mode_resizes_the_screen_in_the_layout() {
+    let mut screens = laptop_and_external();
+    screens[1].modes = vec![
+        mode(2560, 1440, 59.95, true, true),
+        mode(1920, 1080, 60.0, false, false),
+    ];
+    let (mut app, _) = start(screens);
+
+    press(&mut app, KeyCode::Char('m'));
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Char('h'));
+
+    assert_eq!(app.screens[1].size(), (1920, 1080));
+    assert_eq!(app.screens[0].new_position, Some((1920, 0)));
+    assert!(find(&draw(&mut app), "1920x1080").is_some());
+}
+
+#[test]
+fn picked_modes_follow_their_screen_across_hotplugs() {
+    let mut screens = laptop_and_two_externals();
+    screens[2].modes = vec![
+        mode(1920, 1080, 60.0, true, true),
+        mode(1280, 720, 60.0, false, false),
+    ];
+    let (mut app, backend) = start(screens.clone());
+
+    press(&mut app, KeyCode::Tab);
+    assert_eq!(app.screens[app.selected].name, "DP-2");
+    press(&mut app, KeyCode::Char('m'));
+    press(&mut app, KeyCode::Down);
+
+    // HDMI-1 is unplugged, DP-2 moves up in the list
+    screens.remove(1);
+    backend.set_screens(screens);
+    app.tick();
+    assert!(app.mode_picker.show);
+
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.screens[1].name, "DP-2");
+    assert_eq!(
+        app.screens[1].new_mode,
+        Some(mode(1280, 720, 60.0, false, false))
+    );
+    assert!(app.screens[0].new_mode.is_none());
+}
+
+#[test]
+fn unplugging_the_screen_closes_its_mode_picker() {
+    let mut screens = laptop_and_external();
+    screens[1].modes = vec![mode(2560, 1440, 59.95, true, true)];
+    let (mut app, backend) = start(screens);
+
+    press(&mut app, KeyCode::Char('m'));
+    backend.set_screens(vec![screen("eDP-1", true, (1920, 1080), (0, 0))]);
+    app.tick();
+
+    assert!(!app.mode_picker.show);
+}
+
+#[test]
+fn escape_closes_the_mode_picker() {
+    let mut screens = laptop_and_external();
+    screens[1].modes = vec![mode(2560, 1440, 59.95, true, true)];
+    let (mut app, _) = start(screens);
+
+    press(&mut app, KeyCode::Char('m'));
+    press(&mut app, KeyCode::Esc);
+
+    assert!(!app.mode_picker.show);
+    assert!(app.screens[1].new_mode.is_none());
+}
+
+#[test]
+fn rotates_and_reflects_the_selected_screen() {
+    let (mut app, backend) = start(laptop_and_external());
+
+    press(&mut app, KeyCode::Char('r'));
+    assert_eq!(app.screens[1].pending_rotation(), Rotation::Left);
+    assert_eq!(app.screens[1].size(), (1440, 2560));
+
+    press(&mut app, KeyCode::Char('r'));
+    press(&mut app, KeyCode::Char('r'));
+    press(&mut app, KeyCode::Char('r'));
+    assert_eq!(app.screens[1].pending_rotation(), Rotation::Normal);
+    assert!(!app.screens[1].has_pending_changes());
+
+    press(&mut app, KeyCode::Char('r'));
+    press(&mut app, KeyCode::Char('x'));
+    assert_eq!(app.screens[1].pending_reflection(), Reflection::X);
+    assert!(find(&draw(&mut app), "rotate left / reflect x").is_some());
+
+    press(&mut app, KeyCode::Enter);
+    app.tick();
+    assert_eq!(backend.applied().len(), 1);
+    assert_eq!(app.screens[1].rotation, Rotation::Left);
+    assert_eq!(app.screens[1].reflection, Reflection::X);
+}
+
+#[test]
+fn rotated_screens_are_placed_with_swapped_dimensions() {
+    let (mut app, _) = start(laptop_and_external());
+
+    press(&mut app, KeyCode::Char('r'));
+    press(&mut app, KeyCode::Char('h'));
+
+    // The portrait screen is 1440 pixels wide
+    assert_eq!(app.screens[1].new_position, Some((0, 0)));
+    assert_eq!(app.screens[0].new_position, Some((1440, 0)));
+
+    press(&mut app, KeyCode::Char('k'));
+    assert_eq!(app.screens[1].new_position, Some((0, 0)));
+    assert_eq!(app.screens[0].new_position, Some((0, 2560)));
+}
+
+fn type_text(app: &mut App, text: &str) {
+    for c in text.chars() {
+        press(app, KeyCode::Char(c));
+    }
+}
+
+#[test]
+fn saves_and_restores_profiles() {
+    let dir = tempfile::tempdir().unwrap();
+    let (mut app, backend) = start(laptop_and_two_externals());
+    app.profiles = Profiles::new(Some(dir.path().to_path_buf()));
+
+    press(&mut app, KeyCode::Char('p'));
+    assert!(app.profiles.show);
+    assert!(find(&draw(&mut app), "No saved profile").is_some());
+
+    press(&mut app, KeyCode::Char('s'));
+    type_text(&mut app, "deskk");
+    press(&mut app, KeyCode::Backspace);
+    assert!(find(&draw(&mut app), "Name: desk_").is_some());
+    press(&mut app, KeyCode::Enter);
+
+    assert!(dir.path().join("desk.toml").exists());
+    assert!(find(&draw(&mut app), "eDP-1, HDMI-1, DP-2").is_some());
+    press(&mut app, KeyCode::Esc);
+    assert!(!app.profiles.show);
+
+    // Rearrange the screens, then restore the saved layout
+    press(&mut app, KeyCode::Char('h'));
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Char('y'));
+    app.tick();
+    assert_eq!(app.screens[1].position, (0, 0));
+
+    press(&mut app, KeyCode::Char('p'));
+    press(&mut app, KeyCode::Enter);
+    assert!(!app.profiles.show);
+
+    app.tick();
+    let positions: Vec<(u16, u16)> = app.screens.iter().map(|s| s.position).collect();
+    assert_eq!(positions, vec![(0, 0), (1920, 0), (4480, 0)]);
+    assert_eq!(backend.applied().len(), 2);
+}
+
+#[test]
+fn restoring_a_profile_turns_off_missing_screens() {
+    let dir = tempfile::tempdir().unwrap();
+    let (mut app, backend) = start(laptop_and_external());
+    app.profiles = Profiles::new(Some(dir.path().to_path_buf()));
+    app.save_profile("laptop").unwrap();
+
+    backend.set_screens(laptop_and_two_externals());
+    app.tick();
+
+    press(&mut app, KeyCode::Char('p'));
+    press(&mut app, KeyCode::Enter);
+    app.tick();
+
+    assert!(app.screens[0].enabled && app.screens[1].enabled);
+    assert!(!app.screens[2].enabled);
+}
+
+#[test]
+fn dry_run_does_not_claim_a_profile_was_restored() {
+    let dir = tempfile::tempdir().unwrap();
+    let (mut app, backend) = start(laptop_and_external());
+    app.profiles = Profiles::new(Some(dir.path().to_path_buf()));
+    app.save_profile("laptop").unwrap();
+    app.dry_run = true;
+
+    press(&mut app, KeyCode::Char('p'));
+    let (sender, receiver) = mpsc::channel();
+    handle_key_events(
+        KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+        &mut app,
+        sender,
+    )
+    .unwrap();
+
+    assert!(backend.applied().is_empty());
+    match receiver.try_recv() {
+        Ok(tuix::event::Event::Notification(notification)) => {
+            assert_eq!(
+                notification.message,
+                "Dry run, the changes were not applied"
+            )
+        }
+        _ => panic!("expected a notification"),
+    }
+}
+
+#[test]
+fn profile_keys_do_not_move_screens() {
+    let dir = tempfile::tempdir().unwrap();
+    let (mut app, _) = start(laptop_and_external());
+    app.profiles = Profiles::new(Some(dir.path().to_path_buf()));
+
+    press(&mut app, KeyCode::Char('p'));
+    type_text(&mut app, "hjkl");
+
+    assert!(app.screens.iter().all(|s| !s.has_pending_changes()));
+}
+
+#[test]
+fn renders_every_screen() {
+    let (mut app, _) = start(laptop_and_two_externals());
+
+    let lines = draw(&mut app);
+
+    let (edp, _) = find(&lines, "eDP-1").unwrap();
+    let (hdmi, _) = find(&lines, "HDMI-1").unwrap();
+    let (dp, _) = find(&lines, "DP-2").unwrap();
+    assert!(edp < hdmi && hdmi < dp);
+    assert!(find(&lines, "2560x1440").is_some());
+    assert!(find(&lines, "anchor").is_some());
+}
+
+#[test]
+fn renders_the_pending_layout() {
+    let (mut app, _) = start(laptop_and_external());
+
+    press(&mut app, KeyCode::Char('j'));
+    let lines = draw(&mut app);
+
+    let (_, edp) = find(&lines, "eDP-1").unwrap();
+    let (_, hdmi) = find(&lines, "HDMI-1").unwrap();
+    assert!(hdmi > edp);
+}
+
+#[test]
+fn renders_the_help_popup() {
+    let (mut app, _) = start(laptop_and_external());
+
+    press(&mut app, KeyCode::Char('?'));
+    let lines = draw(&mut app);
+    assert!(find(&lines, "Help").is_some());
+
+    press(&mut app, KeyCode::Esc);
+    let lines = draw(&mut app);
+    assert!(find(&lines, "Help").is_none());
+}
+
+#[test]
+fn applies_the_matching_profile_on_hotplug() {
+    let dir = tempfile::tempdir().unwrap();
+    let (mut app, backend) = start(laptop_and_external());
+    app.profiles = Profiles::new(Some(dir.path().to_path_buf()));
+
+    // Docked with the external monitor on the left
+    press(&mut app, KeyCode::Char('h'));
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Char('y'));
+    app.tick();
+    app.save_profile("docked").unwrap();
+
+    // Undocking does not match any profile
+    backend.set_screens(vec![screen("eDP-1", true, (1920, 1080), (0, 0))]);
+    app.tick();
+    assert_eq!(backend.applied().len(), 1);
+
+    // Docking again restores the saved layout
+    backend.set_screens(laptop_and_external());
+    app.tick();
+    assert_eq!(backend.applied().len(), 2);
+    assert!(app.screens.iter().all(|s| !s.has_pending_changes()));
+    let lines = draw(&mut app);
+    assert!(find(&lines, "Profile docked applied").is_some());
+    assert!(find(&lines, "Preview").is_none());
+
+    let positions: Vec<(u16, u16)> = app.screens.iter().map(|s| s.position).collect();
+    assert_eq!(positions, vec![(2560, 0), (0, 0)]);
+}
+
+#[test]
+fn keeps_the_applied_configuration_once_confirmed() {
+    let (mut app, backend) = start(laptop_and_external());
+
+    press(&mut app, KeyCode::Char('h'));
+    press(&mut app, KeyCode::Enter);
+    assert!(find(&draw(&mut app), "Reverting in 15s").is_some());
+
+    // Other keys are ignored while waiting for the confirmation
+    press(&mut app, KeyCode::Char('l'));
+    assert_eq!(app.screens[1].location, Some(Location::LEFT));
+
+    press(&mut app, KeyCode::Char('y'));
+    assert!(app.confirmation.is_none());
+    assert!(app.screens.iter().all(|s| !s.has_pending_changes()));
+    assert_eq!(app.screens[1].position, (0, 0));
+    assert!(find(&draw(&mut app), "Preview").is_none());
+
+    // Nothing is left to apply
+    press(&mut app, KeyCode::Enter);
+    assert!(app.confirmation.is_none());
+    assert_eq!(backend.applied().len(), 1);
+}
+
+#[test]
+fn reverts_the_applied_configuration_on_demand() {
+    let (mut app, backend) = start(laptop_and_external());
+
+    press(&mut app, KeyCode::Char('h'));
+    press(&mut app, KeyCode::Char('r'));
+    press(&mut app, KeyCode::Enter);
+    app.tick();
+    assert_eq!(app.screens[1].position, (0, 0));
+    assert_eq!(app.screens[1].rotation, Rotation::Left);
+
+    press(&mut app, KeyCode::Char('n'));
+    assert!(app.confirmation.is_none());
+    app.tick();
+
+    let positions: Vec<(u16, u16)> = app.screens.iter().map(|s| s.position).collect();
+    assert_eq!(positions, vec![(0, 0), (1920, 0)]);
+    assert_eq!(app.screens[1].rotation, Rotation::Normal);
+    assert!(app.screens.iter().all(|s| !s.has_pending_changes()));
+    assert_eq!(backend.applied().len(), 2);
+}
+
+#[test]
+fn reverts_the_applied_configuration_after_the_timeout() {
+    let (mut app, _) = start(laptop_and_external());
+
+    press(&mut app, KeyCode::Char('e'));
+    press(&mut app, KeyCode::Enter);
+    app.tick();
+    assert!(!app.screens[1].enabled);
+
+    app.confirmation.as_mut().unwrap().deadline = Instant::now();
+    app.tick();
+    app.tick();
+
+    assert!(app.confirmation.is_none());
+    assert!(app.screens[1].enabled);
+    assert!(find(&draw(&mut app), "Configuration reverted").is_some());
+}
+
+#[test]
+fn retries_a_failed_revert() {
+    let (mut app, backend) = start(laptop_and_external());
+
+    press(&mut app, KeyCode::Char('e'));
+    press(&mut app, KeyCode::Enter);
+    app.tick();
+
+    backend.set_failure(Some("Display server unreachable"));
+    app.confirmation.as_mut().unwrap().deadline = Instant::now();
+    app.tick();
+    app.tick();
+
+    assert!(app.confirmation.is_some());
+    assert_eq!(
+        app.notifications
+            .iter()
+            .filter(|n| n.message == "Display server unreachable")
+            .count(),
+        1
+    );
+
+    backend.set_failure(None);
+    app.tick();
+    app.tick();
+
+    assert!(app.confirmation.is_none());
+    assert!(app.screens[1].enabled);
+}
+
+#[test]
+fn previews_the_pending_changes() {
+    let (mut app, _) = start(laptop_and_external());
+    assert!(find(&draw(&mut app), "Preview").is_none());
+
+    press(&mut app, KeyCode::Char('h'));
+
+    let lines = draw(&mut app);
+    assert!(find(&lines, " Preview ").is_some());
+    assert!(find(&lines, "mock apply eDP-1 HDMI-1").is_some());
+}
+
+#[test]
+fn dry_run_never_applies() {
+    let (mut app, backend) = start(laptop_and_external());
+    app.dry_run = true;
+    assert!(find(&draw(&mut app), "Preview (dry run)").is_some());
+
+    press(&mut app, KeyCode::Char('h'));
+    press(&mut app, KeyCode::Enter);
+
+    assert!(app.confirmation.is_none());
+    assert!(backend.applied().is_empty());
+    assert_eq!(app.screens[1].location, Some(Location::LEFT));
+}
+
+#[test]
+fn renders_screens_proportionally() {
+    let (mut app, _) = start(vec![
+        screen("eDP-1", true, (1366, 768), (0, 1392)),
+        screen("DP-1", false, (3840, 2160), (1366, 0)),
+    ]);
+
+    let lines = draw(&mut app);
+    let viewport = app.viewport.unwrap();
+
+    let edp = viewport.to_rect((0, 1392), (1366, 768));
+    let dp = viewport.to_rect((1366, 0), (3840, 2160));
+    assert_eq!(edp.right(), dp.left());
+    assert_eq!(edp.bottom(), dp.bottom());
+    assert!(edp.top() > dp.top());
+    assert!((dp.width as f64 / edp.width as f64 - 3840.0 / 1366.0).abs() < 0.2);
+
+    // Labels are drawn in the screens
+    let (column, row) = find(&lines, "1366x768").unwrap();
+    assert!(edp.contains((column as u16, row as u16).into()));
+    assert!(find(&lines, "primary, anchor").is_some());
+
+    // The transform goes both ways, for the mouse
+    let (x, y) = viewport.to_layout(dp.left(), dp.top());
+    assert!((x - 1366.0).abs() < 60.0 && y.abs() < 120.0);
+}
+
+#[test]
+fn nudges_the_selected_screen() {
+    let (mut app, _) = start(laptop_and_external());
+
+    press(&mut app, KeyCode::Right);
+    assert_eq!(app.screens[1].pending_position(), (1930, 0));
+    assert_eq!(app.screens[1].location, None);
+
+    press_with_modifiers(&mut app, KeyCode::Down, KeyModifiers::SHIFT);
+    assert_eq!(app.screens[1].pending_position(), (1930, 100));
+
+    // Moving left of the origin shifts the whole layout
+    for _ in 0..30 {
+        press_with_modifiers(&mut app, KeyCode::Left, KeyModifiers::SHIFT);
+    }
+    assert_eq!(app.screens[1].pending_position(), (0, 100));
+    assert!(app.screens[0].pending_position().0 > 0);
+}
+
+#[test]
+fn nudging_snaps_to_centres_and_edges() {
+    let (mut app, _) = start(laptop_and_external());
+    press(&mut app, KeyCode::Tab);
+    assert_eq!(app.screens[app.selected].name, "eDP-1");
+
+    press_with_modifiers(&mut app, KeyCode::Down, KeyModifiers::SHIFT);
+    assert_eq!(app.screens[0].pending_position(), (0, 100));
+
+    // Centred on the 1440px tall screen
+    press_with_modifiers(&mut app, KeyCode::Down, KeyModifiers::SHIFT);
+    assert_eq!(app.screens[0].pending_position(), (0, 180));
+
+    // Bottom edges aligned
+    press_with_modifiers(&mut app, KeyCode::Down, KeyModifiers::SHIFT);
+    press_with_modifiers(&mut app, KeyCode::Down, KeyModifiers::SHIFT);
+    assert_eq!(app.screens[0].pending_position(), (0, 360));
+
+    // Moving away is not pulled back
+    press(&mut app, KeyCode::Up);
+    assert_eq!(app.screens[0].pending_position(), (0, 350));
+}
+
+#[test]
+fn cycles_the_alignment_with_the_anchor() {
+    let (mut app, backend) = start(laptop_and_external());
+
+    let positions = |app: &App| -> Vec<(u16, u16)> {
+        app.screens.iter().map(|s| s.pending_position()).collect()
+    };
+
+    press(&mut app, KeyCode::Char('c'));
+    assert_eq!(positions(&app), vec![(0, 180), (1920, 0)]);
+
+    press(&mut app, KeyCode::Char('c'));
+    assert_eq!(positions(&app), vec![(0, 360), (1920, 0)]);
+
+    press(&mut app, KeyCode::Char('c'));
+    assert_eq!(positions(&app), vec![(0, 0), (1920, 0)]);
+
+    press(&mut app, KeyCode::Char('c'));
+    press(&mut app, KeyCode::Enter);
+    app.tick();
+    assert_eq!(app.screens[0].position, (0, 180));
+    assert_eq!(backend.applied().len(), 1);
+}
+
+#[test]
+fn clicking_selects_a_screen() {
+    let (mut app, _) = start(laptop_and_external());
+    draw(&mut app);
+
+    mouse(&mut app, MouseEventKind::Down(MouseButton::Left), 10, 10);
+    mouse(&mut app, MouseEventKind::Up(MouseButton::Left), 10, 10);
+
+    assert_eq!(app.screens[app.selected].name, "eDP-1");
+    assert_eq!(app.screens[app.anchor].name, "HDMI-1");
+    assert!(app.screens.iter().all(|s| !s.has_pending_changes()));
+
+    // Clicking outside of the screens changes nothing
+    mouse(&mut app, MouseEventKind::Down(MouseButton::Left), 50, 28);
+    assert_eq!(app.screens[app.selected].name, "eDP-1");
+    assert!(app.drag.is_none());
+}
+
+#[test]
+fn drags_a_screen_and_snaps_it_on_drop() {
+    let (mut app, _) = start(laptop_and_external());
+    draw(&mut app);
+
+    mouse(&mut app, MouseEventKind::Down(MouseButton::Left), 60, 10);
+    assert_eq!(app.screens[app.selected].name, "HDMI-1");
+
+    // Free move while dragging
+    mouse(&mut app, MouseEventKind::Drag(MouseButton::Left), 60, 14);
+    let (x, y) = app.screens[1].pending_position();
+    assert_eq!(x, 1920);
+    assert!((350..370).contains(&y));
+    assert_eq!(app.screens[0].pending_position(), (0, 0));
+
+    // Dropped right below the laptop screen
+    mouse(&mut app, MouseEventKind::Drag(MouseButton::Left), 18, 22);
+    mouse(&mut app, MouseEventKind::Up(MouseButton::Left), 18, 22);
+    assert_eq!(app.screens[1].new_position, Some((0, 1080)));
+    assert_eq!(app.screens[0].pending_position(), (0, 0));
+    assert!(app.drag.is_none());
+}
+
+#[test]
+fn sets_the_primary_screen() {
+    let (mut app, backend) = start(laptop_and_external());
+
+    press(&mut app, KeyCode::Char('P'));
+    assert!(app.screens[1].pending_primary());
+    assert!(!app.screens[0].pending_primary());
+
+    let lines = draw(&mut app);
+    let (_, hdmi) = find(&lines, "HDMI-1").unwrap();
+    let (_, primary) = find(&lines, "primary").unwrap();
+    assert!(primary > hdmi);
+
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Char('y'));
+    app.tick();
+    assert!(app.screens[1].is_primary && !app.screens[0].is_primary);
+    assert_eq!(backend.applied().len(), 1);
+}
+
+#[test]
+fn disabled_screens_can_not_be_primary() {
+    let (mut app, _) = start(vec![
+        screen("eDP-1", true, (1920, 1080), (0, 0)),
+        disabled_screen("HDMI-1", (2560, 1440)),
+    ]);
+
+    press(&mut app, KeyCode::Char('P'));
+    assert!(app.screens.iter().all(|s| !s.has_pending_changes()));
+}
+
+#[test]
+fn disabling_the_primary_screen_hands_the_role_over() {
+    let (mut app, _) = start(laptop_and_external());
+
+    press(&mut app, KeyCode::Tab);
+    press(&mut app, KeyCode::Char('e'));
+
+    assert!(!app.screens[0].is_enabled() && !app.screens[0].pending_primary());
+    assert!(app.screens[1].pending_primary());
+}
+
+#[test]
+fn warns_when_there_is_no_primary_screen() {
+    let (mut app, _) = start(vec![
+        screen("HDMI-1", false, (2560, 1440), (0, 0)),
+        screen("DP-1", false, (1920, 1080), (2560, 0)),
+    ]);
+
+    assert_eq!(app.selected, 1);
+    assert_eq!(app.anchor, 0);
+    assert!(find(&draw(&mut app), "No primary output").is_some());
+}
+
+fn laptop_and_projector() -> Vec<Screen> {
+    vec![
+        Screen {
+            modes: vec![
+                mode(1920, 1080, 60.0, true, true),
+                mode(1280, 720, 60.0, false, false),
+            ],
+            ..screen("eDP-1", true, (1920, 1080), (0, 0))
+        },
+        Screen {
+            modes: vec![
+                mode(1280, 720, 60.0, true, false),
+                mode(1024, 768, 60.0, false, true),
+            ],
+            ..screen("HDMI-1", false, (1280, 720), (1920, 0))
+        },
+    ]
+}
+
+#[test]
+fn mirrors_the_anchor_with_a_common_mode() {
+    let (mut app, _) = start(vec![
+        Screen {
+            modes: vec![mode(1920, 1080, 60.0, true, true)],
+            ..screen("eDP-1", true, (1920, 1080), (0, 0))
+        },
+        Screen {
+            modes: vec![
+                mode(2560, 1440, 60.0, true, true),
+                mode(1920, 1080, 50.0, false, false),
+                mode(1920, 1080, 60.0, false, false),
+            ],
+            ..screen("HDMI-1", false, (2560, 1440), (1920, 0))
+        },
+    ]);
+
+    press(&mut app, KeyCode::Char('M'));
+
+    let hdmi = &app.screens[1];
+    assert_eq!(hdmi.location, Some(Location::SAME));
+    assert_eq!(hdmi.new_position, Some((0, 0)));
+    assert_eq!(hdmi.new_mode, Some(mode(1920, 1080, 60.0, false, false)));
+    assert_eq!(hdmi.pending_scale(), 1.0);
+
+    let lines = draw(&mut app);
+    assert!(find(&lines, "eDP-1").is_some());
+    assert!(find(&lines, "mirror of eDP-1").is_some());
+}
+
+#[test]
+fn mirrors_a_scaled_anchor_at_its_size() {
+    let (mut app, _) = start(vec![
+        Screen {
+            scale: 2.0,
+            modes: vec![
+                mode(3840, 2160, 60.0, true, true),
+                mode(1920, 1080, 60.0, false, false),
+            ],
+            ..screen("eDP-1", true, (3840, 2160), (0, 0))
+        },
+        Screen {
+            modes: vec![mode(1920, 1080, 60.0, true, true)],
+            ..screen("HDMI-1", false, (1920, 1080), (1920, 0))
+        },
+    ]);
+
+    press(&mut app, KeyCode::Char('M'));
+
+    // Keeping the scale of the anchor would only cover a quarter of it
+    let hdmi = &app.screens[1];
+    assert_eq!(hdmi.new_mode, Some(mode(1920, 1080, 60.0, true, true)));
+    assert_eq!(hdmi.pending_scale(), 1.0);
+    assert_eq!(hdmi.size(), app.screens[0].size());
+}
+
+#[test]
+fn mirrors_the_anchor_with_a_scale_without_common_mode() {
+    let mut screens = laptop_and_projector();
+    screens[0].modes.truncate(1);
+    let (mut app, _) = start(screens);
+
+    press(&mut app, KeyCode::Char('M'));
+
+    let hdmi = &app.screens[1];
+    assert_eq!(hdmi.location, Some(Location::SAME));
+    assert_eq!(hdmi.new_mode, Some(mode(1024, 768, 60.0, false, true)));
+    assert_eq!(hdmi.pending_scale(), 1024.0 / 1920.0);
+}
+
+#[test]
+fn mirroring_twice_places_the_screen_back() {
+    let (mut app, _) = start(laptop_and_projector());
+
+    press(&mut app, KeyCode::Char('M'));
+    assert_eq!(
+        app.screens[1].new_mode,
+        Some(mode(1280, 720, 60.0, true, false))
+    );
+    assert_eq!(app.screens[1].new_position, Some((0, 0)));
+
+    press(&mut app, KeyCode::Char('M'));
+    assert_eq!(app.screens[1].location, Some(Location::RIGHT));
+    assert_eq!(app.screens[1].new_position, Some((1920, 0)));
+    assert_eq!(app.screens[1].new_mode, None);
+
+    press(&mut app, KeyCode::Char('e'));
+    press(&mut app, KeyCode::Char('M'));
+    assert!(!app.screens[1].is_enabled());
+    assert_ne!(app.screens[1].location, Some(Location::SAME));
+}
+
+#[test]
+fn places_an_applied_mirror_back() {
+    let (mut app, _) = start(laptop_and_projector());
+
+    press(&mut app, KeyCode::Char('M'));
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Char('y'));
+    assert_eq!(app.screens[1].position, (0, 0));
+
+    // Only the applied layout tells it is a mirror once it is kept
+    assert_eq!(app.screens[1].location, None);
+
+    press(&mut app, KeyCode::Char('M'));
+    assert_eq!(app.screens[1].location, Some(Location::RIGHT));
+    assert_eq!(app.screens[1].new_position, Some((1920, 0)));
+}
+
+#[test]
+fn scales_the_selected_screen_without_overlapping() {
+    let (mut app, _) = start(laptop_and_two_externals());
+
+    press(&mut app, KeyCode::Char('+'));
+    press(&mut app, KeyCode::Char('+'));
+
+    let hdmi = &app.screens[1];
+    assert_eq!(hdmi.pending_scale(), 1.5);
+    assert_eq!(hdmi.size(), (1707, 960));
+    assert_eq!(hdmi.pending_position(), (1920, 0));
+    assert_eq!(app.screens[2].pending_position(), (1920 + 1707, 0));
+    assert!(find(&draw(&mut app), "scale 1.5").is_some());
+
+    for _ in 0..10 {
+        press(&mut app, KeyCode::Char('-'));
+    }
+    assert_eq!(app.screens[1].pending_scale(), 0.5);
+    assert_eq!(app.screens[2].pending_position(), (1920 + 5120, 0));
+}